@@ -0,0 +1,479 @@
+//! ACPI Multiple APIC Description Table (MADT) parsing and generation for
+//! the GIC.
+//!
+//! The ACPI specification: <https://uefi.org/specifications>
+
+use crate::{GicCpuInterface, GicDistributor};
+
+/// Signature of the MADT.
+const MADT_SIGNATURE: &[u8; 4] = b"APIC";
+
+/// Length of the standard ACPI table header.
+const ACPI_HEADER_LEN: usize = 36;
+
+/// Offset of the first interrupt controller structure in the MADT.
+const MADT_ENTRIES_OFFSET: usize = ACPI_HEADER_LEN + 8;
+
+/// Type of the GIC CPU Interface (GICC) structure.
+pub const MADT_TYPE_GICC: u8 = 0xb;
+
+/// Type of the GIC Distributor (GICD) structure.
+pub const MADT_TYPE_GICD: u8 = 0xc;
+
+/// Minimum length of a GICC structure, as of ACPI 5.1. It holds all the
+/// fields of [`MadtGicc`].
+const MADT_GICC_MIN_LEN: usize = 76;
+
+/// Length of a GICC structure written by [`MadtWriter`], as of ACPI 6.0.
+const MADT_GICC_LEN: usize = 80;
+
+/// Length of a GICD structure.
+const MADT_GICD_LEN: usize = 24;
+
+/// Revision of the MADT written by [`MadtWriter`], as of ACPI 6.0.
+const MADT_REVISION: u8 = 4;
+
+/// Errors of MADT parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MadtError {
+    /// The table does not have the `APIC` signature.
+    BadSignature,
+    /// The table is shorter than its header says, or one of its structures
+    /// runs past its end or is shorter than its type requires.
+    Truncated,
+    /// The bytes of the table do not sum to zero.
+    BadChecksum,
+    /// The buffer to write to is too small.
+    BufferTooSmall,
+}
+
+/// A GIC CPU Interface (GICC) structure of the MADT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MadtGicc {
+    /// The GIC CPU interface number.
+    pub cpu_interface_number: u32,
+    /// The ACPI processor UID, matching the processor objects of the DSDT.
+    pub acpi_processor_uid: u32,
+    /// The flags, see [`Self::FLAG_ENABLED`].
+    pub flags: u32,
+    /// The GSIV of the performance monitoring interrupt.
+    pub performance_interrupt_gsiv: u32,
+    /// The physical address of the CPU interface registers (GICC).
+    pub base_address: u64,
+    /// The physical address of the virtual CPU interface registers (GICV).
+    pub gicv_base_address: u64,
+    /// The physical address of the virtual interface control registers
+    /// (GICH).
+    pub gich_base_address: u64,
+    /// The GSIV of the virtual GIC maintenance interrupt.
+    pub vgic_maintenance_gsiv: u32,
+    /// The affinity (MPIDR) of the processor.
+    pub mpidr: u64,
+}
+
+impl MadtGicc {
+    /// The processor is enabled.
+    pub const FLAG_ENABLED: u32 = 1 << 0;
+    /// The performance monitoring interrupt is edge-triggered.
+    pub const FLAG_PERFORMANCE_INTERRUPT_EDGE: u32 = 1 << 1;
+    /// The virtual GIC maintenance interrupt is edge-triggered.
+    pub const FLAG_VGIC_MAINTENANCE_EDGE: u32 = 1 << 2;
+
+    /// Whether the processor is enabled.
+    pub fn enabled(&self) -> bool {
+        self.flags & Self::FLAG_ENABLED != 0
+    }
+
+    fn parse(entry: &[u8]) -> Option<Self> {
+        if entry.len() < MADT_GICC_MIN_LEN {
+            return None;
+        }
+        Some(Self {
+            cpu_interface_number: le32(entry, 4)?,
+            acpi_processor_uid: le32(entry, 8)?,
+            flags: le32(entry, 12)?,
+            performance_interrupt_gsiv: le32(entry, 20)?,
+            base_address: le64(entry, 32)?,
+            gicv_base_address: le64(entry, 40)?,
+            gich_base_address: le64(entry, 48)?,
+            vgic_maintenance_gsiv: le32(entry, 56)?,
+            mpidr: le64(entry, 68)?,
+        })
+    }
+
+    fn encode(&self) -> [u8; MADT_GICC_LEN] {
+        let mut entry = [0; MADT_GICC_LEN];
+        entry[0] = MADT_TYPE_GICC;
+        entry[1] = MADT_GICC_LEN as u8;
+        entry[4..8].copy_from_slice(&self.cpu_interface_number.to_le_bytes());
+        entry[8..12].copy_from_slice(&self.acpi_processor_uid.to_le_bytes());
+        entry[12..16].copy_from_slice(&self.flags.to_le_bytes());
+        entry[20..24].copy_from_slice(&self.performance_interrupt_gsiv.to_le_bytes());
+        entry[32..40].copy_from_slice(&self.base_address.to_le_bytes());
+        entry[40..48].copy_from_slice(&self.gicv_base_address.to_le_bytes());
+        entry[48..56].copy_from_slice(&self.gich_base_address.to_le_bytes());
+        entry[56..60].copy_from_slice(&self.vgic_maintenance_gsiv.to_le_bytes());
+        entry[68..76].copy_from_slice(&self.mpidr.to_le_bytes());
+        entry
+    }
+}
+
+/// A GIC Distributor (GICD) structure of the MADT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MadtGicd {
+    /// The GIC ID.
+    pub gic_id: u32,
+    /// The physical address of the distributor registers (GICD).
+    pub base_address: u64,
+    /// The GIC version, `2` for GICv2, or `0` if it must be detected from
+    /// the hardware.
+    pub gic_version: u8,
+}
+
+impl MadtGicd {
+    fn parse(entry: &[u8]) -> Option<Self> {
+        if entry.len() < MADT_GICD_LEN {
+            return None;
+        }
+        Some(Self {
+            gic_id: le32(entry, 4)?,
+            base_address: le64(entry, 8)?,
+            gic_version: entry[20],
+        })
+    }
+
+    fn encode(&self) -> [u8; MADT_GICD_LEN] {
+        let mut entry = [0; MADT_GICD_LEN];
+        entry[0] = MADT_TYPE_GICD;
+        entry[1] = MADT_GICD_LEN as u8;
+        entry[4..8].copy_from_slice(&self.gic_id.to_le_bytes());
+        entry[8..16].copy_from_slice(&self.base_address.to_le_bytes());
+        entry[20] = self.gic_version;
+        entry
+    }
+}
+
+/// A validated MADT.
+#[derive(Debug, Clone, Copy)]
+pub struct Madt<'a> {
+    table: &'a [u8],
+}
+
+impl<'a> Madt<'a> {
+    /// Validates the signature, length and checksum of a raw MADT, and the
+    /// lengths of its interrupt controller structures.
+    pub fn parse(table: &'a [u8]) -> Result<Self, MadtError> {
+        if table.get(..4) != Some(MADT_SIGNATURE) {
+            return Err(MadtError::BadSignature);
+        }
+        let len = le32(table, 4).ok_or(MadtError::Truncated)? as usize;
+        if len < MADT_ENTRIES_OFFSET {
+            return Err(MadtError::Truncated);
+        }
+        let table = table.get(..len).ok_or(MadtError::Truncated)?;
+        if table.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)) != 0 {
+            return Err(MadtError::BadChecksum);
+        }
+        let mut off = MADT_ENTRIES_OFFSET;
+        while off < len {
+            let header = table.get(off..off + 2).ok_or(MadtError::Truncated)?;
+            let min_len = match header[0] {
+                MADT_TYPE_GICC => MADT_GICC_MIN_LEN,
+                MADT_TYPE_GICD => MADT_GICD_LEN,
+                _ => 2,
+            };
+            let entry_len = header[1] as usize;
+            if entry_len < min_len || off + entry_len > len {
+                return Err(MadtError::Truncated);
+            }
+            off += entry_len;
+        }
+        Ok(Self { table })
+    }
+
+    /// Iterates over the interrupt controller structures, as their type and
+    /// raw bytes.
+    pub fn entries(&self) -> impl Iterator<Item = (u8, &'a [u8])> + 'a {
+        let table = self.table;
+        let mut off = MADT_ENTRIES_OFFSET;
+        core::iter::from_fn(move || {
+            let header = table.get(off..off + 2)?;
+            let entry = table.get(off..off + (header[1] as usize).max(2))?;
+            off += entry.len();
+            Some((header[0], entry))
+        })
+    }
+
+    /// Iterates over the GICC structures.
+    pub fn giccs(&self) -> impl Iterator<Item = MadtGicc> + 'a {
+        self.entries()
+            .filter(|&(ty, _)| ty == MADT_TYPE_GICC)
+            .filter_map(|(_, entry)| MadtGicc::parse(entry))
+    }
+
+    /// Returns the GICD structure.
+    pub fn gicd(&self) -> Option<MadtGicd> {
+        self.entries()
+            .filter(|&(ty, _)| ty == MADT_TYPE_GICD)
+            .find_map(|(_, entry)| MadtGicd::parse(entry))
+    }
+
+    /// Returns the GIC CPU interface number of the processor with the given
+    /// affinity (MPIDR).
+    pub fn cpu_interface_of_mpidr(&self, mpidr: u64) -> Option<u32> {
+        // Only the affinity fields are compared.
+        const AFF_MASK: u64 = 0xff_00ff_ffff;
+        self.giccs()
+            .find(|gicc| gicc.mpidr & AFF_MASK == mpidr & AFF_MASK)
+            .map(|gicc| gicc.cpu_interface_number)
+    }
+
+    /// Constructs the GIC distributor.
+    ///
+    /// `phys_to_virt` translates the physical base address from the table to
+    /// the address the registers are mapped at.
+    pub fn distributor(&self, phys_to_virt: impl Fn(usize) -> *mut u8) -> Option<GicDistributor> {
+        let gicd = self.gicd()?;
+        Some(GicDistributor::new(phys_to_virt(
+            gicd.base_address as usize,
+        )))
+    }
+
+    /// Constructs the GIC CPU interface, from the first enabled GICC
+    /// structure.
+    ///
+    /// `phys_to_virt` translates the physical base address from the table to
+    /// the address the registers are mapped at.
+    pub fn cpu_interface(
+        &self,
+        phys_to_virt: impl Fn(usize) -> *mut u8,
+    ) -> Option<GicCpuInterface> {
+        let gicc = self.giccs().find(MadtGicc::enabled)?;
+        Some(GicCpuInterface::new(phys_to_virt(
+            gicc.base_address as usize,
+        )))
+    }
+
+    /// Records the CPU interface number of each enabled processor in the
+    /// distributor, see [`GicDistributor::set_cpu_interface`]. Logical CPU
+    /// IDs are assigned in the order of the GICC structures.
+    pub fn register_cpus(&self, gicd: &GicDistributor) {
+        for (cpu_id, gicc) in self.giccs().filter(MadtGicc::enabled).enumerate() {
+            gicd.set_cpu_interface(cpu_id, gicc.cpu_interface_number as usize);
+        }
+    }
+}
+
+/// A MADT writer, building the table in a byte buffer without allocation.
+///
+/// The table is checksummed by [`Self::finish`].
+pub struct MadtWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> MadtWriter<'a> {
+    /// Creates a writer building the table in `buf`, and writes its header
+    /// with the given OEM ID and OEM table ID.
+    pub fn new(
+        buf: &'a mut [u8],
+        oem_id: [u8; 6],
+        oem_table_id: [u8; 8],
+    ) -> Result<Self, MadtError> {
+        let header = buf
+            .get_mut(..MADT_ENTRIES_OFFSET)
+            .ok_or(MadtError::BufferTooSmall)?;
+        header.fill(0);
+        header[..4].copy_from_slice(MADT_SIGNATURE);
+        header[8] = MADT_REVISION;
+        header[10..16].copy_from_slice(&oem_id);
+        header[16..24].copy_from_slice(&oem_table_id);
+        // OEM revision
+        header[24..28].copy_from_slice(&1u32.to_le_bytes());
+        // creator ID and revision
+        header[28..32].copy_from_slice(&oem_id[..4]);
+        header[32..36].copy_from_slice(&1u32.to_le_bytes());
+        // the local interrupt controller address and flags are unused on Arm
+        Ok(Self {
+            buf,
+            len: MADT_ENTRIES_OFFSET,
+        })
+    }
+
+    fn push(&mut self, entry: &[u8]) -> Result<(), MadtError> {
+        let end = self.len + entry.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(MadtError::BufferTooSmall)?
+            .copy_from_slice(entry);
+        self.len = end;
+        Ok(())
+    }
+
+    /// Adds a GICC structure.
+    pub fn gicc(&mut self, gicc: &MadtGicc) -> Result<(), MadtError> {
+        self.push(&gicc.encode())
+    }
+
+    /// Adds a GICD structure.
+    pub fn gicd(&mut self, gicd: &MadtGicd) -> Result<(), MadtError> {
+        self.push(&gicd.encode())
+    }
+
+    /// Writes the length and checksum of the table, and returns its length.
+    pub fn finish(self) -> usize {
+        let table = &mut self.buf[..self.len];
+        table[4..8].copy_from_slice(&(self.len as u32).to_le_bytes());
+        table[9] = 0;
+        let sum = table.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+        table[9] = sum.wrapping_neg();
+        self.len
+    }
+}
+
+/// The layout of the virtual GIC exposed to a guest, from which its MADT is
+/// generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VgicMadtConfig {
+    /// The OEM ID of the table.
+    pub oem_id: [u8; 6],
+    /// The OEM table ID of the table.
+    pub oem_table_id: [u8; 8],
+    /// The guest physical address of the virtual distributor.
+    pub gicd_base: u64,
+    /// The guest physical address where the virtual CPU interface (GICV) is
+    /// mapped, which the guest sees as its GICC.
+    pub gicc_base: u64,
+}
+
+impl VgicMadtConfig {
+    /// Writes the MADT of the virtual GIC into `buf`, and returns its length.
+    ///
+    /// Each vCPU is described by an enabled GICC structure with the affinity
+    /// in `vcpu_mpidrs`, and its index as CPU interface number and ACPI
+    /// processor UID.
+    pub fn write_table(&self, buf: &mut [u8], vcpu_mpidrs: &[u64]) -> Result<usize, MadtError> {
+        let mut writer = MadtWriter::new(buf, self.oem_id, self.oem_table_id)?;
+        for (vcpu_id, &mpidr) in vcpu_mpidrs.iter().enumerate() {
+            writer.gicc(&MadtGicc {
+                cpu_interface_number: vcpu_id as u32,
+                acpi_processor_uid: vcpu_id as u32,
+                flags: MadtGicc::FLAG_ENABLED,
+                performance_interrupt_gsiv: 0,
+                base_address: self.gicc_base,
+                gicv_base_address: 0,
+                gich_base_address: 0,
+                vgic_maintenance_gsiv: 0,
+                mpidr,
+            })?;
+        }
+        writer.gicd(&MadtGicd {
+            gic_id: 0,
+            base_address: self.gicd_base,
+            gic_version: 2,
+        })?;
+        Ok(writer.finish())
+    }
+}
+
+fn le32(bytes: &[u8], off: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(off..off + 4)?.try_into().ok()?,
+    ))
+}
+
+fn le64(bytes: &[u8], off: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(off..off + 8)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VGIC: VgicMadtConfig = VgicMadtConfig {
+        oem_id: *b"ARCEOS",
+        oem_table_id: *b"VGICMADT",
+        gicd_base: 0x800_0000,
+        gicc_base: 0x801_0000,
+    };
+
+    #[test]
+    fn write_then_parse() {
+        let mut buf = [0; 512];
+        let len = VGIC.write_table(&mut buf, &[0x0, 0x1, 0x100]).unwrap();
+        assert_eq!(len, MADT_ENTRIES_OFFSET + 3 * MADT_GICC_LEN + MADT_GICD_LEN);
+
+        let madt = Madt::parse(&buf[..len]).unwrap();
+        assert_eq!(madt.giccs().count(), 3);
+        let gicc = madt.giccs().nth(2).unwrap();
+        assert!(gicc.enabled());
+        assert_eq!(gicc.cpu_interface_number, 2);
+        assert_eq!(gicc.base_address, 0x801_0000);
+        assert_eq!(gicc.mpidr, 0x100);
+        assert_eq!(
+            madt.gicd(),
+            Some(MadtGicd {
+                gic_id: 0,
+                base_address: 0x800_0000,
+                gic_version: 2,
+            })
+        );
+        assert_eq!(madt.cpu_interface_of_mpidr(0x8000_0100), Some(2));
+
+        assert_eq!(
+            VGIC.write_table(&mut buf[..100], &[0x0]).err(),
+            Some(MadtError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn parse_bad_tables() {
+        let mut buf = [0; 256];
+        let len = VGIC.write_table(&mut buf, &[0x0]).unwrap();
+        assert_eq!(
+            Madt::parse(&buf[..len - 1]).err(),
+            Some(MadtError::Truncated)
+        );
+        assert_eq!(
+            Madt::parse(&buf[1..len]).err(),
+            Some(MadtError::BadSignature)
+        );
+        buf[MADT_ENTRIES_OFFSET + 4] ^= 1;
+        assert_eq!(Madt::parse(&buf[..len]).err(), Some(MadtError::BadChecksum));
+
+        // a GICC structure shorter than in ACPI 5.1
+        let mut writer = MadtWriter::new(&mut buf, *b"ARCEOS", *b"VGICMADT").unwrap();
+        let mut entry = [0; MADT_GICC_MIN_LEN - 4];
+        entry[0] = MADT_TYPE_GICC;
+        entry[1] = entry.len() as u8;
+        writer.push(&entry).unwrap();
+        let len = writer.finish();
+        assert_eq!(Madt::parse(&buf[..len]).err(), Some(MadtError::Truncated));
+
+        // a structure running past the end of the table
+        let mut writer = MadtWriter::new(&mut buf, *b"ARCEOS", *b"VGICMADT").unwrap();
+        writer.push(&[0x7f, 16, 0, 0, 0, 0, 0, 0]).unwrap();
+        let len = writer.finish();
+        assert_eq!(Madt::parse(&buf[..len]).err(), Some(MadtError::Truncated));
+    }
+
+    #[test]
+    fn parse_acpi_5_1_gicc() {
+        let mut buf = [0; 256];
+        let mut writer = MadtWriter::new(&mut buf, *b"ARCEOS", *b"VGICMADT").unwrap();
+        let mut entry = [0; MADT_GICC_MIN_LEN];
+        entry[0] = MADT_TYPE_GICC;
+        entry[1] = MADT_GICC_MIN_LEN as u8;
+        entry[4..8].copy_from_slice(&3u32.to_le_bytes());
+        entry[12..16].copy_from_slice(&MadtGicc::FLAG_ENABLED.to_le_bytes());
+        entry[68..76].copy_from_slice(&0x101u64.to_le_bytes());
+        writer.push(&entry).unwrap();
+        let len = writer.finish();
+
+        let madt = Madt::parse(&buf[..len]).unwrap();
+        assert_eq!(madt.giccs().count(), 1);
+        assert_eq!(madt.cpu_interface_of_mpidr(0x101), Some(3));
+    }
+}
@@ -0,0 +1,135 @@
+//! SPI affinity balancing.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::{GicDistributor, GIC_MAX_CPUS, GIC_MAX_IRQ, SHARED_IRQ_NUM, SPI_RANGE};
+
+/// Balances the SPI targets across the online CPUs, by the number of times
+/// each SPI has fired.
+///
+/// The interrupt handling path calls [`Self::record`] for each SPI, and
+/// [`Self::rebalance`] is called periodically to retarget the SPIs, so that
+/// each CPU handles about the same number of interrupts. SPIs can be pinned
+/// with [`Self::pin`] to keep their current target.
+///
+/// CPUs are identified by their logical IDs, mapped to CPU interfaces by
+/// [`GicDistributor::cpu_target_mask`].
+pub struct SpiBalancer {
+    counts: [AtomicU32; SHARED_IRQ_NUM],
+    pinned: [AtomicU32; GIC_MAX_IRQ / 32],
+}
+
+impl SpiBalancer {
+    /// Creates a new balancer with all counters cleared and no SPIs pinned.
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        Self {
+            counts: [const { AtomicU32::new(0) }; SHARED_IRQ_NUM],
+            pinned: [const { AtomicU32::new(0) }; GIC_MAX_IRQ / 32],
+        }
+    }
+
+    /// Counts one occurrence of the given interrupt. SGIs and PPIs are
+    /// ignored.
+    pub fn record(&self, irq: usize) {
+        if SPI_RANGE.contains(&irq) {
+            self.counts[irq - SPI_RANGE.start].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The number of occurrences counted for the given SPI since the last
+    /// rebalance, halved at each rebalance.
+    pub fn count(&self, irq: usize) -> u32 {
+        if SPI_RANGE.contains(&irq) {
+            self.counts[irq - SPI_RANGE.start].load(Ordering::Relaxed)
+        } else {
+            0
+        }
+    }
+
+    /// Marks the given SPI as non-migratable, so that its target is never
+    /// changed by [`Self::rebalance`].
+    pub fn pin(&self, irq: usize) {
+        if SPI_RANGE.contains(&irq) {
+            self.pinned[irq / 32].fetch_or(1 << (irq % 32), Ordering::Relaxed);
+        }
+    }
+
+    /// Marks the given SPI as migratable again.
+    pub fn unpin(&self, irq: usize) {
+        if SPI_RANGE.contains(&irq) {
+            self.pinned[irq / 32].fetch_and(!(1 << (irq % 32)), Ordering::Relaxed);
+        }
+    }
+
+    /// Whether the given SPI is pinned.
+    pub fn is_pinned(&self, irq: usize) -> bool {
+        irq < GIC_MAX_IRQ && self.pinned[irq / 32].load(Ordering::Relaxed) & (1 << (irq % 32)) != 0
+    }
+
+    /// Retargets the SPIs that fired since the last rebalance across the
+    /// online CPUs, given as a mask of logical CPU IDs.
+    ///
+    /// The busiest SPIs are assigned first, each to the CPU with the lowest
+    /// load so far, where pinned SPIs count towards the load of the CPUs they
+    /// target. SPIs that did not fire keep their target. The counters are
+    /// then halved, so that older activity weighs less at the next rebalance.
+    ///
+    /// Returns the number of retargeted SPIs.
+    pub fn rebalance(&self, gicd: &GicDistributor, online_cpus: u8) -> usize {
+        if online_cpus == 0 {
+            return 0;
+        }
+        let max_irqs = gicd.max_irqs().min(SPI_RANGE.end);
+        let mut load = [0u64; GIC_MAX_CPUS];
+        let mut spis = [0u16; SHARED_IRQ_NUM];
+        let mut spi_num = 0;
+
+        // The counters keep being increased by the interrupt handlers, so a
+        // snapshot is used throughout.
+        let mut counts = [0u32; SHARED_IRQ_NUM];
+        for irq in SPI_RANGE.start..max_irqs {
+            counts[irq - SPI_RANGE.start] = self.count(irq);
+        }
+        let count_of = |irq: usize| counts[irq - SPI_RANGE.start];
+
+        for irq in SPI_RANGE.start..max_irqs {
+            let count = count_of(irq);
+            if count == 0 {
+                continue;
+            }
+            if self.is_pinned(irq) {
+                let target = gicd.get_target_cpu(irq) as u8;
+                for (cpu, cpu_load) in load.iter_mut().enumerate() {
+                    if target & gicd.cpu_target_mask(cpu) != 0 {
+                        *cpu_load += count as u64;
+                    }
+                }
+            } else {
+                spis[spi_num] = irq as u16;
+                spi_num += 1;
+            }
+        }
+        let spis = &mut spis[..spi_num];
+        spis.sort_unstable_by_key(|&irq| core::cmp::Reverse(count_of(irq as usize)));
+
+        let mut retargeted = 0;
+        for &irq in spis.iter() {
+            let irq = irq as usize;
+            let cpu = (0..GIC_MAX_CPUS)
+                .filter(|cpu| online_cpus & (1 << cpu) != 0)
+                .min_by_key(|&cpu| load[cpu])
+                .unwrap();
+            load[cpu] += count_of(irq) as u64;
+            if gicd.get_target_cpu(irq) != gicd.cpu_target_mask(cpu) as usize {
+                gicd.set_target_to_cpu(irq, cpu);
+                retargeted += 1;
+            }
+        }
+
+        for count in &self.counts {
+            let _ = count.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |c| Some(c / 2));
+        }
+        retargeted
+    }
+}
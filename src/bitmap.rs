@@ -0,0 +1,88 @@
+//! Sets of interrupt IDs.
+
+use core::fmt;
+
+use crate::GIC_MAX_IRQ;
+
+/// A set of interrupt IDs, stored as one bit per interrupt.
+#[derive(Clone, PartialEq, Eq)]
+pub struct IrqBitmap {
+    bits: [u32; GIC_MAX_IRQ / 32],
+}
+
+impl IrqBitmap {
+    /// Creates an empty set.
+    pub const fn new() -> Self {
+        Self {
+            bits: [0; GIC_MAX_IRQ / 32],
+        }
+    }
+
+    /// Creates a set of all interrupts.
+    pub const fn full() -> Self {
+        Self {
+            bits: [u32::MAX; GIC_MAX_IRQ / 32],
+        }
+    }
+
+    /// Adds an interrupt to the set.
+    ///
+    /// Returns `false` if it was already in the set, or is out of range.
+    pub fn insert(&mut self, irq: usize) -> bool {
+        if irq >= GIC_MAX_IRQ {
+            return false;
+        }
+        let (word, mask) = (irq / 32, 1 << (irq % 32));
+        let inserted = self.bits[word] & mask == 0;
+        self.bits[word] |= mask;
+        inserted
+    }
+
+    /// Removes an interrupt from the set.
+    ///
+    /// Returns `false` if it was not in the set.
+    pub fn remove(&mut self, irq: usize) -> bool {
+        if irq >= GIC_MAX_IRQ {
+            return false;
+        }
+        let (word, mask) = (irq / 32, 1 << (irq % 32));
+        let removed = self.bits[word] & mask != 0;
+        self.bits[word] &= !mask;
+        removed
+    }
+
+    /// Whether the set contains the given interrupt.
+    pub fn contains(&self, irq: usize) -> bool {
+        irq < GIC_MAX_IRQ && self.bits[irq / 32] & (1 << (irq % 32)) != 0
+    }
+
+    /// The number of interrupts in the set.
+    pub fn len(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|&word| word == 0)
+    }
+
+    /// Iterates over the interrupts in the set, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..GIC_MAX_IRQ).filter(|&irq| self.contains(irq))
+    }
+}
+
+impl Default for IrqBitmap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for IrqBitmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
@@ -0,0 +1,168 @@
+//! Common interrupt controller interface.
+
+use crate::{GicCpuInterface, GicDistributor, GicDistributorConfig, TriggerMode};
+
+/// An interrupt acknowledged by [`InterruptController::acknowledge`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AckedIrq {
+    /// The interrupt ID.
+    pub id: usize,
+    /// The raw value read when acknowledging the interrupt, which must be
+    /// passed back to [`InterruptController::end_of_interrupt`].
+    ///
+    /// On a GICv2, it also holds the ID of the CPU that requested an SGI.
+    pub raw: u32,
+}
+
+/// The operations of an interrupt controller that an OS needs, independent
+/// of the GIC version.
+///
+/// CPUs are identified by their logical CPU ID. Only the initialization of
+/// the controller takes `&mut self`, so that it can be shared between CPUs.
+pub trait InterruptController {
+    /// Initializes the controller and the interface of the current CPU, whose
+    /// logical ID is `cpu_id`.
+    fn init(&mut self, cpu_id: usize);
+
+    /// Initializes the interface of the current CPU, whose logical ID is
+    /// `cpu_id`, on secondary CPUs.
+    fn init_cpu(&self, cpu_id: usize);
+
+    /// Returns the number of interrupts that the controller supports.
+    fn max_irqs(&self) -> usize;
+
+    /// Enables or disables the given interrupt.
+    fn set_enable(&self, irq: usize, enable: bool);
+
+    /// Checks whether the given interrupt is enabled.
+    fn is_enabled(&self, irq: usize) -> bool;
+
+    /// Sets the priority of the given interrupt, lower values having higher
+    /// priority.
+    fn set_priority(&self, irq: usize, priority: u8);
+
+    /// Returns the priority of the given interrupt.
+    fn priority(&self, irq: usize) -> u8;
+
+    /// Sets the trigger mode of the given interrupt.
+    fn set_trigger_mode(&self, irq: usize, tm: TriggerMode);
+
+    /// Returns the trigger mode of the given interrupt.
+    fn trigger_mode(&self, irq: usize) -> TriggerMode;
+
+    /// Routes the given shared interrupt to the given CPU.
+    fn route_to_cpu(&self, irq: usize, cpu_id: usize);
+
+    /// Sends the given SGI to the given CPU.
+    fn send_ipi(&self, cpu_id: usize, sgi: usize);
+
+    /// Sends the given SGI to all the CPUs except the current one.
+    fn send_ipi_all_except_self(&self, sgi: usize);
+
+    /// Acknowledges the highest priority pending interrupt of the current
+    /// CPU, or returns `None` if there is none.
+    fn acknowledge(&self) -> Option<AckedIrq>;
+
+    /// Completes the processing of an acknowledged interrupt.
+    fn end_of_interrupt(&self, irq: AckedIrq);
+}
+
+/// A GICv2, made of the distributor and the CPU interface of the current CPU.
+pub struct Gic {
+    gicd: GicDistributor,
+    gicc: GicCpuInterface,
+}
+
+impl Gic {
+    /// Combines the distributor and the CPU interface.
+    pub const fn new(gicd: GicDistributor, gicc: GicCpuInterface) -> Self {
+        Self { gicd, gicc }
+    }
+
+    /// Returns the distributor.
+    pub fn distributor(&self) -> &GicDistributor {
+        &self.gicd
+    }
+
+    /// Returns the distributor, for configuration.
+    pub fn distributor_mut(&mut self) -> &mut GicDistributor {
+        &mut self.gicd
+    }
+
+    /// Returns the CPU interface.
+    pub fn cpu_interface(&self) -> &GicCpuInterface {
+        &self.gicc
+    }
+}
+
+impl InterruptController for Gic {
+    fn init(&mut self, cpu_id: usize) {
+        self.gicd.init_with_config(&GicDistributorConfig {
+            cpu_interface_quirks: self.gicc.quirks(),
+            ..Default::default()
+        });
+        self.gicd.register_current_cpu(cpu_id);
+        self.gicc.init();
+    }
+
+    fn init_cpu(&self, cpu_id: usize) {
+        self.gicd.init_cpu();
+        self.gicd.register_current_cpu(cpu_id);
+        self.gicc.init();
+    }
+
+    fn max_irqs(&self) -> usize {
+        self.gicd.max_irqs()
+    }
+
+    fn set_enable(&self, irq: usize, enable: bool) {
+        self.gicd.set_enable(irq, enable);
+    }
+
+    fn is_enabled(&self, irq: usize) -> bool {
+        self.gicd.get_enable(irq)
+    }
+
+    fn set_priority(&self, irq: usize, priority: u8) {
+        self.gicd.set_priority(irq, priority);
+    }
+
+    fn priority(&self, irq: usize) -> u8 {
+        self.gicd.get_priority(irq) as u8
+    }
+
+    fn set_trigger_mode(&self, irq: usize, tm: TriggerMode) {
+        self.gicd.configure_interrupt(irq, tm);
+    }
+
+    fn trigger_mode(&self, irq: usize) -> TriggerMode {
+        self.gicd.trigger_mode(irq)
+    }
+
+    fn route_to_cpu(&self, irq: usize, cpu_id: usize) {
+        self.gicd.set_target_to_cpu(irq, cpu_id);
+    }
+
+    fn send_ipi(&self, cpu_id: usize, sgi: usize) {
+        self.gicd.send_sgi_to_cpu(cpu_id, sgi);
+    }
+
+    fn send_ipi_all_except_self(&self, sgi: usize) {
+        self.gicd.send_sgi_all_except_self(sgi);
+    }
+
+    fn acknowledge(&self) -> Option<AckedIrq> {
+        let raw = self.gicc.iar();
+        let id = (raw & 0x3ff) as usize;
+        if id < 1020 {
+            Some(AckedIrq { id, raw })
+        } else {
+            // spurious
+            None
+        }
+    }
+
+    fn end_of_interrupt(&self, irq: AckedIrq) {
+        self.gicc.complete(irq.raw);
+    }
+}
@@ -0,0 +1,851 @@
+//! Device tree probing of the GIC.
+//!
+//! The flattened device tree format: <https://devicetree-specification.readthedocs.io/>
+
+use core::ffi::CStr;
+use core::fmt::Write;
+
+use crate::{decode_dt_irq, DtIrqSpec};
+use crate::{GicCpuInterface, GicCpuInterfaceLayout, GicDistributor, GicQuirks};
+
+/// Magic number at the start of a flattened device tree blob.
+const FDT_MAGIC: u32 = 0xd00d_feed;
+
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_NOP: u32 = 0x4;
+const FDT_END: u32 = 0x9;
+
+/// Maximum depth of nested nodes supported while walking the tree.
+const FDT_MAX_DEPTH: usize = 16;
+
+/// Length of the FDT header, version 17.
+const FDT_HEADER_LEN: usize = 40;
+
+/// Length of the memory reservation block written by [`FdtWriter`], holding
+/// only its terminating entry.
+const FDT_MEM_RSVMAP_LEN: usize = 16;
+
+/// Compatible strings of GICv2 (and compatible GICv1) nodes.
+pub const GIC_FDT_COMPATIBLES: &[&str] = &[
+    "arm,gic-400",
+    "arm,cortex-a15-gic",
+    "arm,cortex-a9-gic",
+    "arm,gic-v2",
+];
+
+/// Errors of device tree probing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FdtError {
+    /// The blob does not start with the FDT magic number.
+    BadMagic,
+    /// The blob is shorter than its header says, or a structure runs past
+    /// its end.
+    Truncated,
+    /// The structure block contains an unknown token, or nodes nested
+    /// deeper than supported.
+    BadStructure,
+    /// No enabled GIC node has been found.
+    NotFound,
+    /// The `reg` property of the GIC node lacks the distributor or CPU
+    /// interface region, has addresses that do not fit in a `usize`, or
+    /// cannot be translated through the `ranges` of its ancestors.
+    BadReg,
+    /// The buffer to write to is too small.
+    BufferTooSmall,
+    /// A string property is longer than [`FDT_MAX_STRING_LEN`].
+    StringTooLong,
+}
+
+/// The maximum length of a string written by
+/// [`FdtBuilder::property_string`], without its nul terminator.
+pub const FDT_MAX_STRING_LEN: usize = 63;
+
+/// A memory-mapped register region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GicRegion {
+    /// The physical base address.
+    pub base: usize,
+    /// The size in bytes.
+    pub size: usize,
+}
+
+/// The description of a GIC found in a device tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GicFdtInfo {
+    /// The matched compatible string, from [`GIC_FDT_COMPATIBLES`].
+    pub compatible: &'static str,
+    /// The distributor region (GICD).
+    pub gicd: GicRegion,
+    /// The CPU interface region (GICC).
+    pub gicc: GicRegion,
+    /// The virtual interface control region (GICH), if the GIC has the
+    /// Virtualization Extensions.
+    pub gich: Option<GicRegion>,
+    /// The virtual CPU interface region (GICV), if the GIC has the
+    /// Virtualization Extensions.
+    pub gicv: Option<GicRegion>,
+    /// The maintenance interrupt specifier (the `interrupts` property), if
+    /// the GIC has the Virtualization Extensions.
+    pub maintenance_irq: Option<[u32; 3]>,
+}
+
+impl GicFdtInfo {
+    /// Finds the first enabled GIC node in the given flattened device tree
+    /// blob, and extracts its description.
+    ///
+    /// The regions are translated to CPU physical addresses through the
+    /// `ranges` of the ancestors of the node.
+    pub fn probe(dtb: &[u8]) -> Result<Self, FdtError> {
+        let fdt = Fdt::new(dtb)?;
+        let mut off = 0;
+        // #address-cells and #size-cells of each open node, for its children.
+        let mut cells = [(2, 1); FDT_MAX_DEPTH];
+        // `ranges` of each open node, `None` if it has none.
+        let mut ranges = [None; FDT_MAX_DEPTH];
+        let mut depth = 0;
+        let mut node = GicNode::default();
+
+        loop {
+            let token = fdt.token(&mut off)?;
+            // The properties of a node come before its subnodes, so the node
+            // is complete once a subnode or its end is reached.
+            if let (FDT_BEGIN_NODE | FDT_END_NODE, Some(compatible), true) =
+                (token, node.compatible, node.enabled)
+            {
+                // The root node has no parent to describe its `reg`.
+                if depth < 2 {
+                    return Err(FdtError::BadStructure);
+                }
+                // The root node is at depth 1.
+                let bus = Bus {
+                    cells: &cells[1..depth],
+                    ranges: &ranges[1..depth],
+                };
+                return Self::from_node(compatible, &node, &bus);
+            }
+            match token {
+                FDT_BEGIN_NODE => {
+                    fdt.node_name(&mut off)?;
+                    depth += 1;
+                    if depth >= FDT_MAX_DEPTH {
+                        return Err(FdtError::BadStructure);
+                    }
+                    cells[depth] = (2, 1);
+                    ranges[depth] = None;
+                    node = GicNode::default();
+                }
+                FDT_END_NODE => {
+                    if depth == 0 {
+                        return Err(FdtError::BadStructure);
+                    }
+                    node = GicNode::default();
+                    depth -= 1;
+                }
+                FDT_PROP => {
+                    let (name, value) = fdt.property(&mut off)?;
+                    // Properties only belong to nodes.
+                    if depth == 0 {
+                        return Err(FdtError::BadStructure);
+                    }
+                    match name {
+                        b"#address-cells" => cells[depth].0 = be32(value, 0).unwrap_or(2),
+                        b"#size-cells" => cells[depth].1 = be32(value, 0).unwrap_or(1),
+                        b"compatible" => {
+                            node.compatible = value
+                                .split(|&b| b == 0)
+                                .find_map(|s| {
+                                    GIC_FDT_COMPATIBLES.iter().find(|c| c.as_bytes() == s)
+                                })
+                                .copied();
+                        }
+                        b"status" => {
+                            node.enabled = matches!(value, b"okay\0" | b"ok\0");
+                        }
+                        b"ranges" => ranges[depth] = Some(value),
+                        b"reg" => node.reg = value,
+                        b"interrupts" => node.interrupts = value,
+                        _ => {}
+                    }
+                }
+                FDT_NOP => {}
+                FDT_END => return Err(FdtError::NotFound),
+                _ => return Err(FdtError::BadStructure),
+            }
+        }
+    }
+
+    fn from_node(compatible: &'static str, node: &GicNode, bus: &Bus) -> Result<Self, FdtError> {
+        let (address_cells, size_cells) = bus.cells[bus.cells.len() - 1];
+        let address_len = cells_len(address_cells)?;
+        let entry_size = address_len + cells_len(size_cells)?;
+        if address_len == 0 {
+            return Err(FdtError::BadReg);
+        }
+        let mut regions = node.reg.chunks_exact(entry_size).map(|entry| {
+            let base = read_cells(&entry[..address_len])?;
+            let size = read_cells(&entry[address_len..])?;
+            Some(GicRegion {
+                base: usize::try_from(bus.translate(base, size)?).ok()?,
+                size: usize::try_from(size).ok()?,
+            })
+        });
+        let gicd = regions.next().flatten().ok_or(FdtError::BadReg)?;
+        let gicc = regions.next().flatten().ok_or(FdtError::BadReg)?;
+        let gich = regions.next().flatten();
+        let gicv = regions.next().flatten();
+        let maintenance_irq = match (be32(node.interrupts, 0), be32(node.interrupts, 4)) {
+            (Some(kind), Some(number)) => {
+                Some([kind, number, be32(node.interrupts, 8).unwrap_or(0)])
+            }
+            _ => None,
+        };
+        Ok(Self {
+            compatible,
+            gicd,
+            gicc,
+            gich,
+            gicv,
+            maintenance_irq,
+        })
+    }
+
+    /// Decodes the maintenance interrupt specifier, see [`decode_dt_irq`].
+    pub fn maintenance_irq_spec(&self) -> Option<DtIrqSpec> {
+        decode_dt_irq(self.maintenance_irq?)
+    }
+
+    /// Constructs the GIC distributor.
+    ///
+    /// `phys_to_virt` translates the physical base address from the device
+    /// tree to the address the registers are mapped at.
+    pub fn distributor(&self, phys_to_virt: impl Fn(usize) -> *mut u8) -> GicDistributor {
+        GicDistributor::new(phys_to_virt(self.gicd.base))
+    }
+
+    /// Constructs the GIC CPU interface, with the layout detected from the
+    /// size of its region.
+    ///
+    /// `phys_to_virt` translates the physical base address from the device
+    /// tree to the address the registers are mapped at.
+    pub fn cpu_interface(&self, phys_to_virt: impl Fn(usize) -> *mut u8) -> GicCpuInterface {
+        let layout = GicCpuInterfaceLayout::detect(self.gicc.size, GicQuirks::NONE);
+        GicCpuInterface::new_with_layout(phys_to_virt(self.gicc.base), layout)
+    }
+}
+
+/// The buses a node sits on: the `#address-cells`, `#size-cells` and
+/// `ranges` of each of its ancestors, from the root.
+struct Bus<'a, 'b> {
+    cells: &'b [(u32, u32)],
+    ranges: &'b [Option<&'a [u8]>],
+}
+
+impl Bus<'_, '_> {
+    /// Translates the region at `addr` on the innermost bus to a CPU physical
+    /// address, through the `ranges` of each bus but the root.
+    ///
+    /// Returns `None` if a bus has no `ranges`, or if the region is not
+    /// covered by a single `ranges` entry.
+    fn translate(&self, mut addr: u64, size: u64) -> Option<u64> {
+        for level in (1..self.cells.len()).rev() {
+            let ranges = self.ranges[level]?;
+            // An empty `ranges` is an identity mapping.
+            if ranges.is_empty() {
+                continue;
+            }
+            let (child_cells, size_cells) = self.cells[level];
+            let parent_cells = self.cells[level - 1].0;
+            let child_len = cells_len(child_cells).ok()?;
+            let parent_len = cells_len(parent_cells).ok()?;
+            let entry_size = child_len + parent_len + cells_len(size_cells).ok()?;
+            addr = ranges.chunks_exact(entry_size).find_map(|entry| {
+                let child = read_cells(&entry[..child_len])?;
+                let parent = read_cells(&entry[child_len..child_len + parent_len])?;
+                let len = read_cells(&entry[child_len + parent_len..])?;
+                let off = addr.checked_sub(child)?;
+                if off.checked_add(size)? <= len {
+                    parent.checked_add(off)
+                } else {
+                    None
+                }
+            })?;
+        }
+        Some(addr)
+    }
+}
+
+/// Properties of the node being walked.
+struct GicNode<'a> {
+    compatible: Option<&'static str>,
+    enabled: bool,
+    reg: &'a [u8],
+    interrupts: &'a [u8],
+}
+
+impl Default for GicNode<'_> {
+    fn default() -> Self {
+        Self {
+            compatible: None,
+            enabled: true,
+            reg: &[],
+            interrupts: &[],
+        }
+    }
+}
+
+/// A validated flattened device tree blob.
+struct Fdt<'a> {
+    structs: &'a [u8],
+    strings: &'a [u8],
+}
+
+impl<'a> Fdt<'a> {
+    fn new(dtb: &'a [u8]) -> Result<Self, FdtError> {
+        if be32(dtb, 0) != Some(FDT_MAGIC) {
+            return Err(FdtError::BadMagic);
+        }
+        let header = |i: usize| {
+            be32(dtb, i * 4)
+                .map(|v| v as usize)
+                .ok_or(FdtError::Truncated)
+        };
+        let total_size = header(1)?;
+        let (off_struct, off_strings) = (header(2)?, header(3)?);
+        let (size_strings, size_struct) = (header(8)?, header(9)?);
+        let dtb = dtb.get(..total_size).ok_or(FdtError::Truncated)?;
+        Ok(Self {
+            structs: dtb
+                .get(off_struct..off_struct + size_struct)
+                .ok_or(FdtError::Truncated)?,
+            strings: dtb
+                .get(off_strings..off_strings + size_strings)
+                .ok_or(FdtError::Truncated)?,
+        })
+    }
+
+    fn token(&self, off: &mut usize) -> Result<u32, FdtError> {
+        let token = be32(self.structs, *off).ok_or(FdtError::Truncated)?;
+        *off += 4;
+        Ok(token)
+    }
+
+    fn node_name(&self, off: &mut usize) -> Result<&'a [u8], FdtError> {
+        let rest = self.structs.get(*off..).ok_or(FdtError::Truncated)?;
+        let name = CStr::from_bytes_until_nul(rest).map_err(|_| FdtError::Truncated)?;
+        *off += align4(name.to_bytes_with_nul().len());
+        Ok(name.to_bytes())
+    }
+
+    fn property(&self, off: &mut usize) -> Result<(&'a [u8], &'a [u8]), FdtError> {
+        let len = self.token(off)? as usize;
+        let name_off = self.token(off)? as usize;
+        let value = self
+            .structs
+            .get(*off..*off + len)
+            .ok_or(FdtError::Truncated)?;
+        *off += align4(len);
+        let name = self
+            .strings
+            .get(name_off..)
+            .and_then(|s| CStr::from_bytes_until_nul(s).ok())
+            .ok_or(FdtError::Truncated)?;
+        Ok((name.to_bytes(), value))
+    }
+}
+
+/// A builder of flattened device trees, to which nodes can be emitted.
+///
+/// It is implemented by [`FdtWriter`], and can be implemented for the FDT
+/// builder of a hypervisor to emit nodes with
+/// [`VgicFdtConfig::write_node`], whose error must then convert from
+/// [`FdtError`].
+pub trait FdtBuilder {
+    /// The error of the builder.
+    type Error;
+
+    /// Begins a node with the given name.
+    fn begin_node(&mut self, name: &str) -> Result<(), Self::Error>;
+
+    /// Adds a property with the given raw value to the current node.
+    fn property(&mut self, name: &str, value: &[u8]) -> Result<(), Self::Error>;
+
+    /// Ends the current node.
+    fn end_node(&mut self) -> Result<(), Self::Error>;
+
+    /// Adds a property holding a single cell to the current node.
+    fn property_u32(&mut self, name: &str, value: u32) -> Result<(), Self::Error> {
+        self.property(name, &value.to_be_bytes())
+    }
+
+    /// Adds a property holding a single nul-terminated string to the
+    /// current node.
+    ///
+    /// Returns [`FdtError::StringTooLong`] if the string is longer than
+    /// [`FDT_MAX_STRING_LEN`], as it is terminated in a fixed-size buffer.
+    fn property_string(&mut self, name: &str, value: &str) -> Result<(), Self::Error>
+    where
+        Self::Error: From<FdtError>,
+    {
+        let len = value.len();
+        if len > FDT_MAX_STRING_LEN {
+            return Err(FdtError::StringTooLong.into());
+        }
+        let mut buf = [0; FDT_MAX_STRING_LEN + 1];
+        buf[..len].copy_from_slice(value.as_bytes());
+        self.property(name, &buf[..len + 1])
+    }
+}
+
+/// A minimal FDT writer, building a flattened device tree blob without
+/// allocation.
+///
+/// The structure and strings blocks are built in the buffers given to
+/// [`Self::new`], then assembled into a blob by [`Self::finish`].
+pub struct FdtWriter<'a> {
+    structs: &'a mut [u8],
+    struct_len: usize,
+    strings: &'a mut [u8],
+    strings_len: usize,
+    depth: usize,
+}
+
+impl<'a> FdtWriter<'a> {
+    /// Creates a writer building the structure and strings blocks in the
+    /// given buffers.
+    pub fn new(structs: &'a mut [u8], strings: &'a mut [u8]) -> Self {
+        Self {
+            structs,
+            struct_len: 0,
+            strings,
+            strings_len: 0,
+            depth: 0,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) -> Result<(), FdtError> {
+        let end = self.struct_len + align4(bytes.len());
+        let buf = self
+            .structs
+            .get_mut(self.struct_len..end)
+            .ok_or(FdtError::BufferTooSmall)?;
+        buf.fill(0);
+        buf[..bytes.len()].copy_from_slice(bytes);
+        self.struct_len = end;
+        Ok(())
+    }
+
+    /// Returns the offset of the given name in the strings block, adding it
+    /// if it is not there yet.
+    fn string_offset(&mut self, name: &str) -> Result<u32, FdtError> {
+        let mut off = 0;
+        while off < self.strings_len {
+            let s = CStr::from_bytes_until_nul(&self.strings[off..self.strings_len])
+                .map_err(|_| FdtError::BadStructure)?;
+            if s.to_bytes() == name.as_bytes() {
+                return Ok(off as u32);
+            }
+            off += s.to_bytes_with_nul().len();
+        }
+        let end = off + name.len() + 1;
+        let buf = self
+            .strings
+            .get_mut(off..end)
+            .ok_or(FdtError::BufferTooSmall)?;
+        buf[..name.len()].copy_from_slice(name.as_bytes());
+        buf[name.len()] = 0;
+        self.strings_len = end;
+        Ok(off as u32)
+    }
+
+    /// Assembles the blob into `dtb`, and returns its size.
+    ///
+    /// All the nodes must have been ended.
+    pub fn finish(mut self, dtb: &mut [u8]) -> Result<usize, FdtError> {
+        if self.depth != 0 {
+            return Err(FdtError::BadStructure);
+        }
+        self.push(&FDT_END.to_be_bytes())?;
+        let off_struct = FDT_HEADER_LEN + FDT_MEM_RSVMAP_LEN;
+        let off_strings = off_struct + self.struct_len;
+        let total_size = off_strings + self.strings_len;
+        let dtb = dtb.get_mut(..total_size).ok_or(FdtError::BufferTooSmall)?;
+
+        let header = [
+            FDT_MAGIC,
+            total_size as u32,
+            off_struct as u32,
+            off_strings as u32,
+            FDT_HEADER_LEN as u32,
+            17, // version
+            16, // last_comp_version
+            0,  // boot_cpuid_phys
+            self.strings_len as u32,
+            self.struct_len as u32,
+        ];
+        for (i, field) in header.iter().enumerate() {
+            dtb[i * 4..i * 4 + 4].copy_from_slice(&field.to_be_bytes());
+        }
+        dtb[FDT_HEADER_LEN..off_struct].fill(0);
+        dtb[off_struct..off_strings].copy_from_slice(&self.structs[..self.struct_len]);
+        dtb[off_strings..].copy_from_slice(&self.strings[..self.strings_len]);
+        Ok(total_size)
+    }
+}
+
+impl FdtBuilder for FdtWriter<'_> {
+    type Error = FdtError;
+
+    fn begin_node(&mut self, name: &str) -> Result<(), FdtError> {
+        // the tag and the nul-terminated name, padded to 4 bytes
+        let end = self.struct_len + 4 + align4(name.len() + 1);
+        let buf = self
+            .structs
+            .get_mut(self.struct_len..end)
+            .ok_or(FdtError::BufferTooSmall)?;
+        buf.fill(0);
+        buf[..4].copy_from_slice(&FDT_BEGIN_NODE.to_be_bytes());
+        buf[4..4 + name.len()].copy_from_slice(name.as_bytes());
+        self.struct_len = end;
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn property(&mut self, name: &str, value: &[u8]) -> Result<(), FdtError> {
+        let name_off = self.string_offset(name)?;
+        self.push(&FDT_PROP.to_be_bytes())?;
+        self.push(&(value.len() as u32).to_be_bytes())?;
+        self.push(&name_off.to_be_bytes())?;
+        self.push(value)
+    }
+
+    fn end_node(&mut self) -> Result<(), FdtError> {
+        if self.depth == 0 {
+            return Err(FdtError::BadStructure);
+        }
+        self.push(&FDT_END_NODE.to_be_bytes())?;
+        self.depth -= 1;
+        Ok(())
+    }
+}
+
+/// The layout of the virtual GIC exposed to a guest, from which its device
+/// tree node is generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VgicFdtConfig {
+    /// The compatible string, usually one of [`GIC_FDT_COMPATIBLES`].
+    pub compatible: &'static str,
+    /// The guest physical region of the virtual distributor.
+    pub gicd: GicRegion,
+    /// The guest physical region where the virtual CPU interface (GICV) is
+    /// mapped, which the guest sees as its GICC.
+    pub gicc: GicRegion,
+    /// The phandle of the node, referenced by the `interrupt-parent` of the
+    /// other nodes, or `0` for none.
+    pub phandle: u32,
+}
+
+impl VgicFdtConfig {
+    /// Emits the `interrupt-controller` node of the virtual GIC.
+    ///
+    /// `address_cells` and `size_cells` are the `#address-cells` and
+    /// `#size-cells` of the parent node, used to encode the `reg` property.
+    /// Nothing is emitted and [`FdtError::BadReg`] is returned if they are
+    /// not 1 or 2, or if a base or size does not fit in them. Likewise,
+    /// [`FdtError::StringTooLong`] is returned if the compatible string is
+    /// longer than [`FDT_MAX_STRING_LEN`].
+    pub fn write_node<B>(
+        &self,
+        builder: &mut B,
+        address_cells: u32,
+        size_cells: u32,
+    ) -> Result<(), B::Error>
+    where
+        B: FdtBuilder,
+        B::Error: From<FdtError>,
+    {
+        if self.compatible.len() > FDT_MAX_STRING_LEN {
+            return Err(FdtError::StringTooLong.into());
+        }
+        let mut reg = [0; 32];
+        let mut len = 0;
+        for region in [self.gicd, self.gicc] {
+            for (val, cells) in [(region.base, address_cells), (region.size, size_cells)] {
+                let val = val as u64;
+                match cells {
+                    1 if val >> 32 == 0 => {}
+                    2 => {
+                        reg[len..len + 4].copy_from_slice(&((val >> 32) as u32).to_be_bytes());
+                        len += 4;
+                    }
+                    _ => return Err(FdtError::BadReg.into()),
+                }
+                reg[len..len + 4].copy_from_slice(&(val as u32).to_be_bytes());
+                len += 4;
+            }
+        }
+
+        let mut name = NameBuf::new();
+        // `NameBuf` is large enough for any address.
+        let _ = write!(name, "interrupt-controller@{:x}", self.gicd.base);
+        builder.begin_node(name.as_str())?;
+        builder.property_string("compatible", self.compatible)?;
+        builder.property_u32("#interrupt-cells", 3)?;
+        builder.property("interrupt-controller", &[])?;
+        builder.property("reg", &reg[..len])?;
+        if self.phandle != 0 {
+            builder.property_u32("phandle", self.phandle)?;
+        }
+        builder.end_node()
+    }
+}
+
+/// A fixed-size buffer to format node names.
+struct NameBuf {
+    buf: [u8; 48],
+    len: usize,
+}
+
+impl NameBuf {
+    const fn new() -> Self {
+        Self {
+            buf: [0; 48],
+            len: 0,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        // Only `str`s are written to the buffer.
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+impl Write for NameBuf {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let buf = self
+            .buf
+            .get_mut(self.len..self.len + s.len())
+            .ok_or(core::fmt::Error)?;
+        buf.copy_from_slice(s.as_bytes());
+        self.len += s.len();
+        Ok(())
+    }
+}
+
+const fn align4(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn be32(bytes: &[u8], off: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        bytes.get(off..off + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Reads a number of big-endian cells, which must fit in 64 bits.
+fn read_cells(bytes: &[u8]) -> Option<u64> {
+    let mut val: u64 = 0;
+    for cell in bytes.chunks_exact(4) {
+        if val >> 32 != 0 {
+            return None;
+        }
+        val = (val << 32) | be32(cell, 0)? as u64;
+    }
+    Some(val)
+}
+
+/// The length in bytes of a number of cells, at most 4 as in the device tree
+/// specification.
+fn cells_len(cells: u32) -> Result<usize, FdtError> {
+    if cells <= 4 {
+        Ok(cells as usize * 4)
+    } else {
+        Err(FdtError::BadReg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TriggerMode;
+
+    /// The GIC-400 of the BCM2711, see `testdata/bcm2711-gic.dts`.
+    const BCM2711_DTB: &[u8] = include_bytes!("../testdata/bcm2711-gic.dtb");
+
+    /// Builds a blob with a GIC-400 node under a `soc` bus with the given
+    /// `ranges`, or none.
+    fn soc_dtb<'a>(dtb: &'a mut [u8], ranges: Option<&[u32]>) -> &'a [u8] {
+        let (mut structs, mut strings) = ([0; 512], [0; 256]);
+        let mut w = FdtWriter::new(&mut structs, &mut strings);
+        w.begin_node("").unwrap();
+        w.property_u32("#address-cells", 1).unwrap();
+        w.property_u32("#size-cells", 1).unwrap();
+        w.begin_node("soc").unwrap();
+        w.property_u32("#address-cells", 1).unwrap();
+        w.property_u32("#size-cells", 1).unwrap();
+        if let Some(ranges) = ranges {
+            let mut value = [0; 64];
+            for (i, cell) in ranges.iter().enumerate() {
+                value[i * 4..i * 4 + 4].copy_from_slice(&cell.to_be_bytes());
+            }
+            w.property("ranges", &value[..ranges.len() * 4]).unwrap();
+        }
+        w.begin_node("interrupt-controller@1000").unwrap();
+        w.property_string("compatible", "arm,gic-400").unwrap();
+        let mut reg = [0; 16];
+        for (i, cell) in [0x1000u32, 0x1000, 0x2000, 0x2000].iter().enumerate() {
+            reg[i * 4..i * 4 + 4].copy_from_slice(&cell.to_be_bytes());
+        }
+        w.property("reg", &reg).unwrap();
+        w.end_node().unwrap();
+        w.end_node().unwrap();
+        w.end_node().unwrap();
+        let len = w.finish(dtb).unwrap();
+        &dtb[..len]
+    }
+
+    #[test]
+    fn probe_bcm2711() {
+        let info = GicFdtInfo::probe(BCM2711_DTB).unwrap();
+        assert_eq!(info.compatible, "arm,gic-400");
+        // translated through the `ranges` of the `soc` bus
+        assert_eq!(
+            info.gicd,
+            GicRegion {
+                base: 0xff84_1000,
+                size: 0x1000
+            }
+        );
+        assert_eq!(
+            info.gicc,
+            GicRegion {
+                base: 0xff84_2000,
+                size: 0x2000
+            }
+        );
+        assert_eq!(
+            info.gich,
+            Some(GicRegion {
+                base: 0xff84_4000,
+                size: 0x2000
+            })
+        );
+        assert_eq!(
+            info.gicv,
+            Some(GicRegion {
+                base: 0xff84_6000,
+                size: 0x2000
+            })
+        );
+        assert_eq!(info.maintenance_irq, Some([1, 9, 0xf04]));
+        let spec = info.maintenance_irq_spec().unwrap();
+        assert_eq!(spec.id, 25);
+        assert_eq!(spec.trigger_mode, Some(TriggerMode::Level));
+        assert_eq!(spec.cpu_mask, 0xf);
+    }
+
+    /// Writes the node of `vgic` under a root node with the given cells, and
+    /// probes it back.
+    fn write_vgic(
+        vgic: &VgicFdtConfig,
+        address_cells: u32,
+        size_cells: u32,
+    ) -> Result<GicFdtInfo, FdtError> {
+        let (mut structs, mut strings, mut dtb) = ([0; 512], [0; 256], [0; 1024]);
+        let mut w = FdtWriter::new(&mut structs, &mut strings);
+        w.begin_node("")?;
+        w.property_u32("#address-cells", address_cells)?;
+        w.property_u32("#size-cells", size_cells)?;
+        vgic.write_node(&mut w, address_cells, size_cells)?;
+        w.end_node()?;
+        let len = w.finish(&mut dtb)?;
+        GicFdtInfo::probe(&dtb[..len])
+    }
+
+    #[test]
+    fn write_vgic_node() {
+        let mut vgic = VgicFdtConfig {
+            compatible: "arm,gic-400",
+            gicd: GicRegion {
+                base: 0x800_0000,
+                size: 0x1_0000,
+            },
+            gicc: GicRegion {
+                base: 0x801_0000,
+                size: 0x2000,
+            },
+            phandle: 1,
+        };
+        for (address_cells, size_cells) in [(1, 1), (2, 1), (2, 2)] {
+            let info = write_vgic(&vgic, address_cells, size_cells).unwrap();
+            assert_eq!(info.compatible, "arm,gic-400");
+            assert_eq!(info.gicd, vgic.gicd);
+            assert_eq!(info.gicc, vgic.gicc);
+        }
+
+        assert_eq!(write_vgic(&vgic, 3, 1).err(), Some(FdtError::BadReg));
+        assert_eq!(write_vgic(&vgic, 2, 0).err(), Some(FdtError::BadReg));
+        vgic.gicc.base = 0x1_0000_0000;
+        assert!(write_vgic(&vgic, 2, 1).is_ok());
+        assert_eq!(write_vgic(&vgic, 1, 1).err(), Some(FdtError::BadReg));
+
+        vgic.compatible = LONG_STRING;
+        assert_eq!(write_vgic(&vgic, 2, 1).err(), Some(FdtError::StringTooLong));
+    }
+
+    /// A string one byte longer than [`FDT_MAX_STRING_LEN`].
+    const LONG_STRING: &str = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn write_long_strings() {
+        let (mut structs, mut strings) = ([0; 256], [0; 64]);
+        let mut w = FdtWriter::new(&mut structs, &mut strings);
+        w.begin_node("").unwrap();
+        let max = &LONG_STRING[..FDT_MAX_STRING_LEN];
+        assert_eq!(w.property_string("model", max), Ok(()));
+        assert_eq!(
+            w.property_string("model", LONG_STRING),
+            Err(FdtError::StringTooLong)
+        );
+    }
+
+    #[test]
+    fn probe_bad_blobs() {
+        assert_eq!(GicFdtInfo::probe(&[]), Err(FdtError::BadMagic));
+        assert_eq!(
+            GicFdtInfo::probe(&BCM2711_DTB[..8]),
+            Err(FdtError::Truncated)
+        );
+        let truncated = &BCM2711_DTB[..BCM2711_DTB.len() - 1];
+        assert_eq!(GicFdtInfo::probe(truncated), Err(FdtError::Truncated));
+
+        // a property outside of any node
+        let (mut structs, mut strings, mut dtb) = ([0; 128], [0; 64], [0; 256]);
+        let mut w = FdtWriter::new(&mut structs, &mut strings);
+        w.property_string("compatible", "arm,gic-400").unwrap();
+        w.begin_node("").unwrap();
+        w.end_node().unwrap();
+        let len = w.finish(&mut dtb).unwrap();
+        assert_eq!(GicFdtInfo::probe(&dtb[..len]), Err(FdtError::BadStructure));
+    }
+
+    #[test]
+    fn probe_translation() {
+        let mut dtb = [0; 1024];
+        let info = GicFdtInfo::probe(soc_dtb(&mut dtb, Some(&[]))).unwrap();
+        assert_eq!(info.gicd.base, 0x1000);
+
+        let ranges = [0x0, 0x8000_0000, 0x10000];
+        let info = GicFdtInfo::probe(soc_dtb(&mut dtb, Some(&ranges))).unwrap();
+        assert_eq!(info.gicd.base, 0x8000_1000);
+        assert_eq!(info.gicc.base, 0x8000_2000);
+
+        // no `ranges`, or not covering the regions
+        let dtb_none = soc_dtb(&mut dtb, None);
+        assert_eq!(GicFdtInfo::probe(dtb_none), Err(FdtError::BadReg));
+        let ranges = [0x0, 0x8000_0000, 0x1800];
+        let dtb_short = soc_dtb(&mut dtb, Some(&ranges));
+        assert_eq!(GicFdtInfo::probe(dtb_short), Err(FdtError::BadReg));
+    }
+}
@@ -3,19 +3,27 @@
 //! The official documentation: <https://developer.arm.com/documentation/ihi0048/latest/>
 
 use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 
 use tock_registers::interfaces::{Readable, Writeable};
 use tock_registers::register_structs;
 use tock_registers::registers::{ReadOnly, ReadWrite, WriteOnly};
 
 use crate::GIC_CONFIG_BITS;
-use crate::SGI_RANGE;
-use crate::{TriggerMode, GICC_CTLR_EN_BIT, GICD_CTLR_EN_BIT, GIC_MAX_IRQ, SPI_RANGE};
-
-#[cfg(feature = "el2")]
-use crate::GICC_CTLR_EOIMODENS_BIT;
+use crate::{EoiMode, InterruptGroup, TriggerMode, GICD_CTLR_EN_BIT, GIC_MAX_IRQ, SPI_RANGE};
+use crate::{
+    GICC_CTLR_ENGRP1_BIT, GICC_CTLR_EN_BIT, GICC_CTLR_EOIMODENS_BIT, GICC_CTLR_FIQBYPDIS_BIT,
+    GICC_CTLR_IRQBYPDIS_BIT,
+};
+use crate::{GIC_DEFAULT_PRIORITY, GIC_MAX_CPUS, PPI_RANGE, SGI_RANGE};
 
+use crate::bitmap::IrqBitmap;
+use crate::handler_table::{IrqDispatch, IrqHandlerTable};
+use crate::iidr::{GicModel, GiccIidr, GicdIidr};
+use crate::nesting::IrqNestState;
+use crate::quirks::{quirks_of, GicQuirks};
 use crate::regs::{GicdSgirReg, GICD_SGIR};
+use crate::state::{GicCpuInterfaceState, GicDistributorBankedState, GicDistributorState};
 
 register_structs! {
     /// GIC Distributor registers.
@@ -79,16 +87,44 @@ register_structs! {
         (0x0014 => RPR: ReadOnly<u32>),
         /// Highest Priority Pending Interrupt Register.
         (0x0018 => HPPIR: ReadOnly<u32>),
-        (0x001c => _reserved_1),
+        /// Aliased Binary Point Register.
+        (0x001c => ABPR: ReadWrite<u32>),
+        /// Aliased Interrupt Acknowledge Register.
+        (0x0020 => AIAR: ReadOnly<u32>),
+        /// Aliased End of Interrupt Register.
+        (0x0024 => AEOIR: WriteOnly<u32>),
+        /// Aliased Highest Priority Pending Interrupt Register.
+        (0x0028 => AHPPIR: ReadOnly<u32>),
+        (0x002c => _reserved_1),
+        /// Active Priorities Registers.
+        (0x00d0 => APR: [ReadWrite<u32>; 0x4]),
+        /// Non-secure Active Priorities Registers.
+        (0x00e0 => NSAPR: [ReadWrite<u32>; 0x4]),
+        (0x00f0 => _reserved_3),
         /// CPU Interface Identification Register.
         (0x00fc => IIDR: ReadOnly<u32>),
-        (0x0100 => _reserved_2),
+        (0x0100 => @END),
+    }
+}
+
+register_structs! {
+    /// GIC CPU Interface registers in the second 4KB page.
+    #[allow(non_snake_case)]
+    GicCpuInterfaceDirRegs {
         /// Deactivate Interrupt Register.
-        (0x1000 => DIR: WriteOnly<u32>),
-        (0x1004 => @END),
+        (0x0000 => DIR: WriteOnly<u32>),
+        (0x0004 => @END),
     }
 }
 
+/// Offset of the second 4KB page of the CPU interface, holding GICC_DIR, in
+/// the [`GicCpuInterfaceLayout::Contiguous`] layout.
+const GICC_DIR_PAGE_OFFSET: usize = 0x1000;
+
+/// Offset of the last alias of the first 4KB page of the CPU interface, in the
+/// [`GicCpuInterfaceLayout::Aliased64K`] layout.
+const GICC_ALIAS_64K_OFFSET: usize = 0xf000;
+
 /// The GIC distributor.
 ///
 /// The Distributor block performs interrupt prioritization and distribution
@@ -107,9 +143,77 @@ register_structs! {
 /// - visibility of the state of each interrupt
 /// - a mechanism for software to set or clear the pending state of a peripheral
 ///   interrupt.
+///
+/// Runtime operations take `&self`, so a distributor can be shared between
+/// CPUs. The priority and target fields are written with byte accesses, and
+/// the registers whose fields cannot be written alone (GICD_ICFGR and
+/// GICD_IGROUPR, and the priority and target registers on implementations
+/// with [`GicQuirks::NO_BYTE_ACCESS`]) are updated under an internal
+/// spinlock. As it does not mask interrupts, these updates should not be made
+/// from interrupt handlers. Enabling and disabling interrupts is lock-free.
+/// Only initialization and probing, which must not run concurrently with
+/// other operations, take `&mut self`.
 pub struct GicDistributor {
     base: NonNull<GicDistributorRegs>,
     max_irqs: usize,
+    /// Implemented interrupts, all of them until probed.
+    implemented: IrqBitmap,
+    /// Number of implemented priority bits.
+    priority_bits: u8,
+    /// Quirks of the implementation.
+    quirks: GicQuirks,
+    /// CPU interface mask of each logical CPU, `0` if unknown.
+    cpu_interfaces: [AtomicU8; GIC_MAX_CPUS],
+    /// Serializes the read-modify-write of registers shared by several
+    /// interrupts.
+    rmw_lock: AtomicBool,
+}
+
+/// Releases the [`GicDistributor::rmw_lock`] when dropped.
+struct RmwGuard<'a>(&'a AtomicBool);
+
+impl Drop for RmwGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// Configuration of the GIC distributor, used by
+/// [`GicDistributor::init_with_config`].
+///
+/// The [`Default`] configuration targets all SPIs at CPU 0 and configures
+/// them to be edge-triggered, leaving their priority and group untouched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GicDistributorConfig {
+    /// Trigger mode of all SPIs.
+    pub trigger_mode: TriggerMode,
+    /// Priority of all SPIs, or `None` to leave it untouched.
+    pub priority: Option<u8>,
+    /// CPU target mask of all SPIs, used if there are several CPU interfaces.
+    pub target_mask: u8,
+    /// Group of all SPIs, or `None` to leave it untouched.
+    pub group: Option<InterruptGroup>,
+    /// Keep the SPI trigger mode, priority, target and group programmed by
+    /// the firmware, ignoring the fields above. The trigger mode and priority
+    /// of the boot CPU's SGIs and PPIs are kept as well.
+    pub preserve_firmware_config: bool,
+    /// Quirks identified from the CPU interface, see
+    /// [`GicCpuInterface::quirks`], as some implementations can only be told
+    /// apart by GICC_IIDR.
+    pub cpu_interface_quirks: GicQuirks,
+}
+
+impl Default for GicDistributorConfig {
+    fn default() -> Self {
+        Self {
+            trigger_mode: TriggerMode::Edge,
+            priority: None,
+            target_mask: 0x01,
+            group: None,
+            preserve_firmware_config: false,
+            cpu_interface_quirks: GicQuirks::NONE,
+        }
+    }
 }
 
 /// The GIC CPU interface.
@@ -127,6 +231,102 @@ pub struct GicDistributor {
 /// - determining the highest priority pending interrupt for the processor.
 pub struct GicCpuInterface {
     base: NonNull<GicCpuInterfaceRegs>,
+    dir_base: NonNull<GicCpuInterfaceDirRegs>,
+}
+
+/// Layout of the two 4KB pages of the GIC CPU interface in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GicCpuInterfaceLayout {
+    /// The two pages are contiguous, so GICC_DIR is at `base + 0x1000`.
+    Contiguous,
+    /// Each page is aliased over a 64KB frame, so the second page starts at
+    /// `base + 0x10000`, as in some GIC-400 integrations.
+    ///
+    /// The CPU interface is then accessed through the last alias of the first
+    /// page, at `base + 0xf000`, followed by the second page.
+    Aliased64K,
+    /// The second page is at the given address.
+    Split(*mut u8),
+}
+
+impl GicCpuInterfaceLayout {
+    /// Detects the layout from the size of the CPU interface region, as
+    /// given by the device tree or the firmware, and the quirks of the
+    /// implementation.
+    ///
+    /// A region of 128KB or more holds two 64KB frames, and so does any
+    /// region if [`GicQuirks::GICC_ALIAS_64K`] is set.
+    pub fn detect(region_size: usize, quirks: GicQuirks) -> Self {
+        if region_size >= 0x20000 || quirks.contains(GicQuirks::GICC_ALIAS_64K) {
+            Self::Aliased64K
+        } else {
+            Self::Contiguous
+        }
+    }
+}
+
+/// Configuration of the GIC CPU interface, used by
+/// [`GicCpuInterface::init_with_config`].
+///
+/// The [`Default`] configuration enables group 0 interrupts and unmasks all
+/// priority levels. It uses [`EoiMode::Split`] if the `el2` feature is
+/// enabled, and [`EoiMode::Combined`] otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GicCpuInterfaceConfig {
+    /// The EOI mode of the CPU interface.
+    pub eoi_mode: EoiMode,
+    /// Enable the signaling of group 0 interrupts (GICC_CTLR.EnableGrp0).
+    ///
+    /// For Non-secure accesses to a GIC with the Security Extensions, this
+    /// bit enables the signaling of group 1 interrupts instead.
+    pub enable_grp0: bool,
+    /// Enable the signaling of group 1 interrupts (GICC_CTLR.EnableGrp1).
+    ///
+    /// Only effective for Secure accesses, or if the GIC does not implement
+    /// the Security Extensions.
+    pub enable_grp1: bool,
+    /// Disable the bypass IRQ and FIQ signals when the signaling of
+    /// interrupts by the CPU interface is disabled.
+    pub bypass_disable: bool,
+    /// Interrupt priority mask (GICC_PMR). Only interrupts with a higher
+    /// priority (lower value) are signaled.
+    pub priority_mask: u8,
+}
+
+impl Default for GicCpuInterfaceConfig {
+    fn default() -> Self {
+        Self {
+            eoi_mode: if cfg!(feature = "el2") {
+                EoiMode::Split
+            } else {
+                EoiMode::Combined
+            },
+            enable_grp0: true,
+            enable_grp1: false,
+            bypass_disable: false,
+            priority_mask: 0xff,
+        }
+    }
+}
+
+impl GicCpuInterfaceConfig {
+    /// The GICC_CTLR value described by this configuration.
+    const fn ctlr(&self) -> u32 {
+        let mut ctlr = 0;
+        if self.enable_grp0 {
+            ctlr |= GICC_CTLR_EN_BIT;
+        }
+        if self.enable_grp1 {
+            ctlr |= GICC_CTLR_ENGRP1_BIT;
+        }
+        if self.bypass_disable {
+            ctlr |= GICC_CTLR_FIQBYPDIS_BIT | GICC_CTLR_IRQBYPDIS_BIT;
+        }
+        if let EoiMode::Split = self.eoi_mode {
+            ctlr |= GICC_CTLR_EOIMODENS_BIT;
+        }
+        ctlr
+    }
 }
 
 unsafe impl Send for GicDistributor {}
@@ -141,6 +341,11 @@ impl GicDistributor {
         Self {
             base: NonNull::new(base).unwrap().cast(),
             max_irqs: GIC_MAX_IRQ,
+            implemented: IrqBitmap::full(),
+            priority_bits: 8,
+            quirks: GicQuirks::NONE,
+            cpu_interfaces: [const { AtomicU8::new(0) }; GIC_MAX_CPUS],
+            rmw_lock: AtomicBool::new(false),
         }
     }
 
@@ -148,6 +353,37 @@ impl GicDistributor {
         unsafe { self.base.as_ref() }
     }
 
+    fn lock_rmw(&self) -> RmwGuard<'_> {
+        while self
+            .rmw_lock
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+        RmwGuard(&self.rmw_lock)
+    }
+
+    /// Writes the byte-wide field of `int_id` in GICD_IPRIORITYR or
+    /// GICD_ITARGETSR.
+    ///
+    /// These registers are byte-accessible, so the field is written alone,
+    /// unless the implementation has [`GicQuirks::NO_BYTE_ACCESS`].
+    fn write_byte_field(&self, regs: &[ReadWrite<u32>], int_id: usize, val: u8) {
+        let reg = &regs[int_id / 4];
+        let offset = (int_id % 4) * 8;
+        if self.quirks.contains(GicQuirks::NO_BYTE_ACCESS) {
+            let _guard = self.lock_rmw();
+            let mask = 0xff << offset;
+            reg.set((reg.get() & !mask) | ((val as u32) << offset));
+        } else {
+            let ptr = (reg as *const ReadWrite<u32>).cast::<u8>().cast_mut();
+            // SAFETY: the register is an MMIO word, of which the field is
+            // the `int_id % 4`th byte in little-endian order.
+            unsafe { ptr.add(int_id % 4).write_volatile(val) };
+        }
+    }
+
     /// The number of implemented CPU interfaces.
     pub fn cpu_num(&self) -> usize {
         ((self.regs().TYPER.get() as usize >> 5) & 0b111) + 1
@@ -158,10 +394,59 @@ impl GicDistributor {
         ((self.regs().TYPER.get() as usize & 0b11111) + 1) * 32
     }
 
+    /// Checks whether the given interrupt is implemented.
+    ///
+    /// Until [`Self::probe_implemented_irqs`] is called, all the interrupts
+    /// below [`Self::max_irqs`] are assumed to be implemented. Configuration
+    /// calls on unimplemented interrupts are ignored.
+    pub fn is_implemented(&self, int_id: usize) -> bool {
+        int_id < self.max_irqs && self.implemented.contains(int_id)
+    }
+
+    /// The set of implemented interrupts, see [`Self::is_implemented`].
+    pub fn implemented_irqs(&self) -> &IrqBitmap {
+        &self.implemented
+    }
+
+    /// Probes which interrupts are implemented.
+    ///
+    /// The enable bits of unimplemented interrupts are read-as-zero, so each
+    /// GICD_ISENABLER is written with all ones and read back, then the
+    /// interrupts that were disabled are disabled again. SGIs whose enable
+    /// bits are read-only are reported as implemented.
+    ///
+    /// It is called by [`Self::init`] while all interrupts are disabled, and
+    /// should otherwise be called with interrupts masked on the CPU.
+    pub fn probe_implemented_irqs(&mut self) -> &IrqBitmap {
+        let mut implemented = IrqBitmap::new();
+        for i in 0..self.max_irqs() / 32 {
+            let prev = self.regs().ISENABLER[i].get();
+            self.regs().ISENABLER[i].set(u32::MAX);
+            let mut bits = self.regs().ISENABLER[i].get();
+            self.regs().ICENABLER[i].set(bits & !prev);
+            if i == 0 {
+                // SGIs are always implemented
+                bits |= (1 << SGI_RANGE.end) - 1;
+            }
+            for bit in 0..32 {
+                if bits & (1 << bit) != 0 {
+                    implemented.insert(i * 32 + bit);
+                }
+            }
+        }
+        self.implemented = implemented;
+        &self.implemented
+    }
+
     /// Configures the trigger mode for the given interrupt.
-    pub fn configure_interrupt(&mut self, vector: usize, tm: TriggerMode) {
-        // Only configurable for SPI interrupts
-        if vector >= self.max_irqs || vector < SPI_RANGE.start {
+    ///
+    /// SGIs are always edge-triggered. Whether the trigger mode of a PPI or
+    /// SPI is programmable is IMPLEMENTATION DEFINED, and can be checked with
+    /// [`Self::is_trigger_mode_configurable`]. The trigger mode of PPIs is
+    /// banked for each CPU.
+    pub fn configure_interrupt(&self, vector: usize, tm: TriggerMode) {
+        // Not configurable for SGI interrupts
+        if !self.is_implemented(vector) || vector < PPI_RANGE.start {
             return;
         }
 
@@ -169,6 +454,7 @@ impl GicDistributor {
         // 16 irqs encoded per ICFGR register
         let reg_idx = vector >> 4;
         let bit_shift = ((vector & 0xf) << 1) + 1;
+        let _guard = self.lock_rmw();
         let mut reg_val = self.regs().ICFGR[reg_idx].get();
         match tm {
             TriggerMode::Edge => reg_val |= 1 << bit_shift,
@@ -177,9 +463,42 @@ impl GicDistributor {
         self.regs().ICFGR[reg_idx].set(reg_val);
     }
 
+    /// Get the trigger mode of the given interrupt. (read GICD_ICFGR)
+    pub fn trigger_mode(&self, vector: usize) -> TriggerMode {
+        if vector < PPI_RANGE.start {
+            return TriggerMode::Edge;
+        }
+        let reg_idx = vector >> 4;
+        let bit_shift = ((vector & 0xf) << 1) + 1;
+        if self.regs().ICFGR[reg_idx].get() & (1 << bit_shift) != 0 {
+            TriggerMode::Edge
+        } else {
+            TriggerMode::Level
+        }
+    }
+
+    /// Checks whether the trigger mode of the given interrupt is programmable.
+    ///
+    /// It is never the case for SGIs. For PPIs and SPIs, the ICFGR field is
+    /// probed by toggling its trigger mode bit and reading it back, then
+    /// restoring it. The interrupt should be disabled while probing.
+    pub fn is_trigger_mode_configurable(&self, vector: usize) -> bool {
+        if !self.is_implemented(vector) || vector < PPI_RANGE.start {
+            return false;
+        }
+        let reg_idx = vector >> 4;
+        let bit = 1 << (((vector & 0xf) << 1) + 1);
+        let _guard = self.lock_rmw();
+        let reg_val = self.regs().ICFGR[reg_idx].get();
+        self.regs().ICFGR[reg_idx].set(reg_val ^ bit);
+        let configurable = self.regs().ICFGR[reg_idx].get() & bit != reg_val & bit;
+        self.regs().ICFGR[reg_idx].set(reg_val);
+        configurable
+    }
+
     /// Enables or disables the given interrupt.
-    pub fn set_enable(&mut self, vector: usize, enable: bool) {
-        if vector >= self.max_irqs {
+    pub fn set_enable(&self, vector: usize, enable: bool) {
+        if !self.is_implemented(vector) {
             return;
         }
         let reg = vector / 32;
@@ -192,14 +511,14 @@ impl GicDistributor {
     }
 
     /// Check the given interrupt is Enable or disable.
-    pub fn get_enable(&mut self, vector: usize) -> bool {
+    pub fn get_enable(&self, vector: usize) -> bool {
         let reg = vector / 32;
         let mask = 1 << (vector % 32);
         self.regs().ISENABLER[reg].get() & mask != 0
     }
 
     /// Send ipi to processor specified by `dest_cpu_id`.
-    pub fn send_sgi(&mut self, dest_cpu_id: usize, sgi_num: usize) {
+    pub fn send_sgi(&self, dest_cpu_id: usize, sgi_num: usize) {
         self.regs().SGIR.write(
             GICD_SGIR::TargetListFilter::ForwardToCPUTargetList
                 + GICD_SGIR::CPUTargetList.val(dest_cpu_id as _)
@@ -208,7 +527,7 @@ impl GicDistributor {
     }
 
     /// Sends an IPI to every processor, excluding the current one.
-    pub fn send_sgi_all_except_self(&mut self, sgi_num: usize) {
+    pub fn send_sgi_all_except_self(&self, sgi_num: usize) {
         self.regs().SGIR.write(
             GICD_SGIR::TargetListFilter::ForwardToAllExceptRequester
                 + GICD_SGIR::SGIINTID.val(sgi_num as _),
@@ -216,7 +535,7 @@ impl GicDistributor {
     }
 
     /// Sends an IPI to the current processor.
-    pub fn send_sgi_to_self(&mut self, sgi_num: usize) {
+    pub fn send_sgi_to_self(&self, sgi_num: usize) {
         self.regs().SGIR.write(
             GICD_SGIR::TargetListFilter::ForwardToRequester + GICD_SGIR::SGIINTID.val(sgi_num as _),
         );
@@ -230,15 +549,62 @@ impl GicDistributor {
     }
 
     /// Set interrupt priority.
-    pub fn set_priority(&mut self, int_id: usize, priority: u8) {
-        let idx = (int_id * 8) / 32;
-        let offset = (int_id * 8) % 32;
-        let mask: u32 = 0xff << offset;
+    pub fn set_priority(&self, int_id: usize, priority: u8) {
+        if !self.is_implemented(int_id) {
+            return;
+        }
+        self.write_byte_field(&self.regs().IPRIORITYR, int_id, priority);
+    }
 
-        let prev_reg_val = self.regs().IPRIORITYR[idx].get();
-        // clear target int_id priority and set its priority.
-        let reg_val = (prev_reg_val & !mask) | (((priority as u32) << offset) & mask);
-        self.regs().IPRIORITYR[idx].set(reg_val);
+    /// Probes the number of implemented priority bits.
+    ///
+    /// A GIC implements between 4 and 8 priority bits, the lower bits of the
+    /// priority fields being read-as-zero. They are probed by writing `0xff`
+    /// to the priority field of SGI 0 and reading it back, then restoring it.
+    ///
+    /// If SGI 0 is Secure, its priority field reads as zero from the
+    /// Non-secure state, so the result is clamped to the architectural
+    /// minimum of 4 bits.
+    ///
+    /// It is called by [`Self::init`], and the result is available from
+    /// [`Self::priority_bits`].
+    pub fn probe_priority_bits(&mut self) -> u8 {
+        const MIN_PRIORITY_BITS: u8 = 4;
+        let prev = self.get_priority(0) as u8;
+        self.set_priority(0, 0xff);
+        let bits = ((self.get_priority(0) as u8).leading_ones() as u8).max(MIN_PRIORITY_BITS);
+        self.set_priority(0, prev);
+        self.priority_bits = bits;
+        bits
+    }
+
+    /// The number of implemented priority bits, as probed by
+    /// [`Self::probe_priority_bits`]. It is 8 until then.
+    pub fn priority_bits(&self) -> u8 {
+        self.priority_bits
+    }
+
+    /// The number of distinct priority levels, i.e. `2^priority_bits`.
+    pub fn priority_levels(&self) -> usize {
+        1 << self.priority_bits
+    }
+
+    /// Set interrupt priority by its logical level.
+    ///
+    /// Level `0` is the highest priority and `priority_levels() - 1` the
+    /// lowest. Levels are mapped onto the implemented high bits of the
+    /// priority field, so that no level is truncated. Higher levels are
+    /// clamped to the lowest priority.
+    pub fn set_priority_level(&self, int_id: usize, level: usize) {
+        let level = level.min(self.priority_levels() - 1);
+        let priority = ((level as u32) << (8 - self.priority_bits)) as u8;
+        self.set_priority(int_id, priority);
+    }
+
+    /// Get interrupt priority as a logical level, see
+    /// [`Self::set_priority_level`].
+    pub fn get_priority_level(&self, int_id: usize) -> usize {
+        self.get_priority(int_id) >> (8 - self.priority_bits)
     }
 
     /// Get interrupt target cpu.
@@ -249,15 +615,124 @@ impl GicDistributor {
     }
 
     /// Set interrupt target cpu.
-    pub fn set_target_cpu(&mut self, int_id: usize, target: u8) {
-        let idx = (int_id * 8) / 32;
-        let offset = (int_id * 8) % 32;
-        let mask: u32 = 0xff << offset;
+    pub fn set_target_cpu(&self, int_id: usize, target: u8) {
+        if !self.is_implemented(int_id) {
+            return;
+        }
+        self.write_byte_field(&self.regs().ITARGETSR, int_id, target);
+    }
+
+    /// Returns the CPU interface mask of the calling CPU.
+    ///
+    /// GICD_ITARGETSR0-7 are banked and read-only, and each of their fields
+    /// reads as the mask of the calling CPU's interface. If they read as zero,
+    /// the GIC only has one CPU interface, and `0b1` is returned.
+    pub fn current_cpu_interface_mask(&self) -> u8 {
+        let mask = (0..PPI_RANGE.end / 4)
+            .map(|i| self.regs().ITARGETSR[i].get())
+            .fold(0, |acc, val| acc | val);
+        match mask.to_le_bytes().into_iter().fold(0, |acc, b| acc | b) {
+            0 => 1,
+            mask => mask,
+        }
+    }
+
+    /// Records the calling CPU's interface as the one of logical CPU
+    /// `cpu_id`, and returns its interface number.
+    ///
+    /// It should be called by each CPU when it comes online, before the other
+    /// CPUs target it with [`Self::send_sgi_to_cpu`] or
+    /// [`Self::set_target_to_cpu`].
+    pub fn register_current_cpu(&self, cpu_id: usize) -> Option<usize> {
+        let mask = self.current_cpu_interface_mask();
+        self.cpu_interfaces
+            .get(cpu_id)?
+            .store(mask, Ordering::Release);
+        Some(mask.trailing_zeros() as usize)
+    }
+
+    /// Records interface number `interface` as the one of logical CPU
+    /// `cpu_id`, e.g. when it is known from the firmware.
+    pub fn set_cpu_interface(&self, cpu_id: usize, interface: usize) {
+        if let (Some(slot), true) = (self.cpu_interfaces.get(cpu_id), interface < GIC_MAX_CPUS) {
+            slot.store(1 << interface, Ordering::Release);
+        }
+    }
+
+    /// Returns the interface number of logical CPU `cpu_id`, if recorded.
+    pub fn cpu_interface(&self, cpu_id: usize) -> Option<usize> {
+        match self.cpu_interfaces.get(cpu_id)?.load(Ordering::Acquire) {
+            0 => None,
+            mask => Some(mask.trailing_zeros() as usize),
+        }
+    }
+
+    /// Returns the CPU target mask of logical CPU `cpu_id`.
+    ///
+    /// If its interface has not been recorded, the interface number is
+    /// assumed to be the same as the logical CPU ID. Returns `0` if it is not
+    /// recorded and `cpu_id` is not a valid interface number.
+    pub fn cpu_target_mask(&self, cpu_id: usize) -> u8 {
+        match self.cpu_interface(cpu_id) {
+            Some(interface) => 1 << interface,
+            None if cpu_id < GIC_MAX_CPUS => 1 << cpu_id,
+            None => 0,
+        }
+    }
 
-        let prev_reg_val = self.regs().ITARGETSR[idx].get();
-        // clear target int_id target and set its target.
-        let reg_val: u32 = (prev_reg_val & !mask) | (((target as u32) << offset) & mask);
-        self.regs().ITARGETSR[idx].set(reg_val);
+    /// Send ipi to logical CPU `cpu_id`.
+    pub fn send_sgi_to_cpu(&self, cpu_id: usize, sgi_num: usize) {
+        let mask = self.cpu_target_mask(cpu_id);
+        if mask == 0 {
+            return;
+        }
+        self.send_sgi(mask as usize, sgi_num);
+    }
+
+    /// Set interrupt target to logical CPU `cpu_id`.
+    pub fn set_target_to_cpu(&self, int_id: usize, cpu_id: usize) {
+        let mask = self.cpu_target_mask(cpu_id);
+        if mask == 0 {
+            return;
+        }
+        self.set_target_cpu(int_id, mask);
+    }
+
+    /// Migrates the SPIs targeting only logical CPU `cpu_id` to the CPUs in
+    /// the `new_targets` mask, before that CPU goes offline.
+    ///
+    /// Returns the set of migrated SPIs, so that they can be targeted again at
+    /// the CPU with [`Self::restore_migrated_spis`] when it comes back online.
+    ///
+    /// If `new_targets` is empty or contains the CPU going offline, nothing
+    /// is migrated and an empty set is returned, as the SPIs would otherwise
+    /// be left without an online target. The same goes if the CPU has no
+    /// known interface.
+    pub fn migrate_spis(&self, cpu_id: usize, new_targets: u8) -> IrqBitmap {
+        let mask = self.cpu_target_mask(cpu_id);
+        let mut migrated = IrqBitmap::new();
+        if mask == 0 || new_targets == 0 || new_targets & mask != 0 {
+            return migrated;
+        }
+        for int_id in SPI_RANGE.start..self.max_irqs() {
+            if self.get_target_cpu(int_id) == mask as usize {
+                self.set_target_cpu(int_id, new_targets);
+                migrated.insert(int_id);
+            }
+        }
+        migrated
+    }
+
+    /// Targets the SPIs migrated by [`Self::migrate_spis`] at logical CPU
+    /// `cpu_id` again, after it comes back online.
+    pub fn restore_migrated_spis(&self, cpu_id: usize, migrated: &IrqBitmap) {
+        let mask = self.cpu_target_mask(cpu_id);
+        if mask == 0 {
+            return;
+        }
+        for int_id in migrated.iter() {
+            self.set_target_cpu(int_id, mask);
+        }
     }
 
     /// Set interrupt state to pending or not.
@@ -329,41 +804,217 @@ impl GicDistributor {
         self.regs().IIDR.get()
     }
 
+    /// Get the decoded iidr register.
+    pub fn iidr(&self) -> GicdIidr {
+        GicdIidr::from_raw(self.get_iidr())
+    }
+
+    /// Identifies the GIC implementation from GICD_IIDR.
+    pub fn model(&self) -> GicModel {
+        GicModel::from_gicd_iidr(self.iidr())
+    }
+
+    /// The quirks of the implementation.
+    ///
+    /// They are looked up in [`GIC_QUIRKS`](crate::GIC_QUIRKS) by
+    /// [`Self::init`], in addition to the ones given by [`Self::add_quirks`].
+    pub fn quirks(&self) -> GicQuirks {
+        self.quirks
+    }
+
+    /// Adds quirks that cannot be detected from GICD_IIDR, e.g. the ones of
+    /// the [model](GicCpuInterface::model) identified by the CPU interface.
+    pub fn add_quirks(&mut self, quirks: GicQuirks) {
+        self.quirks |= quirks;
+    }
+
+    /// Get interrupt group. (read GICD_IGROUPR)
+    pub fn get_group(&self, int_id: usize) -> InterruptGroup {
+        let reg = int_id / 32;
+        let mask = 1 << (int_id % 32);
+        if self.regs().IGROUPR[reg].get() & mask != 0 {
+            InterruptGroup::Group1
+        } else {
+            InterruptGroup::Group0
+        }
+    }
+
+    /// Set interrupt group. (write GICD_IGROUPR)
+    pub fn set_group(&self, int_id: usize, group: InterruptGroup) {
+        if !self.is_implemented(int_id) {
+            return;
+        }
+        let reg = int_id / 32;
+        let mask = 1 << (int_id % 32);
+        let _guard = self.lock_rmw();
+        let prev_reg_val = self.regs().IGROUPR[reg].get();
+        match group {
+            InterruptGroup::Group0 => self.regs().IGROUPR[reg].set(prev_reg_val & !mask),
+            InterruptGroup::Group1 => self.regs().IGROUPR[reg].set(prev_reg_val | mask),
+        }
+    }
+
     /// Determines whether the corresponding interrupt is edge-triggered or level-sensitive.
     pub fn set_icfgr(&self, int_id: usize, cfg: u8) {
         let reg_ind = (int_id * GIC_CONFIG_BITS) / 32;
         let off = (int_id * GIC_CONFIG_BITS) % 32;
         let mask = 0b11 << off;
 
+        let _guard = self.lock_rmw();
         let icfgr = self.regs().ICFGR[reg_ind].get();
         self.regs().ICFGR[reg_ind].set((icfgr & !mask) | (((cfg as u32) << off) & mask));
     }
 
+    /// Saves the distributor registers shared by all CPUs, for system
+    /// suspend.
+    ///
+    /// Only the registers of the SPIs below [`Self::max_irqs`] are saved. The
+    /// SGI and PPI registers are banked, see [`Self::save_banked_state`].
+    pub fn save_state(&self) -> GicDistributorState {
+        let max_irqs = self.max_irqs();
+        let regs = self.regs();
+        let mut state = GicDistributorState {
+            max_irqs,
+            ctlr: regs.CTLR.get(),
+            igroupr: [0; GIC_MAX_IRQ / 32],
+            isenabler: [0; GIC_MAX_IRQ / 32],
+            ipriorityr: [0; GIC_MAX_IRQ / 4],
+            itargetsr: [0; GIC_MAX_IRQ / 4],
+            icfgr: [0; GIC_MAX_IRQ / 16],
+        };
+        for i in SPI_RANGE.start / 32..max_irqs / 32 {
+            state.igroupr[i] = regs.IGROUPR[i].get();
+            state.isenabler[i] = regs.ISENABLER[i].get();
+        }
+        for i in SPI_RANGE.start / 4..max_irqs / 4 {
+            state.ipriorityr[i] = regs.IPRIORITYR[i].get();
+            state.itargetsr[i] = regs.ITARGETSR[i].get();
+        }
+        for i in SPI_RANGE.start / 16..max_irqs / 16 {
+            state.icfgr[i] = regs.ICFGR[i].get();
+        }
+        state
+    }
+
+    /// Restores the distributor registers saved by [`Self::save_state`],
+    /// after system resume.
+    ///
+    /// The distributor is disabled while the SPIs are reconfigured, and
+    /// GICD_CTLR is restored last.
+    pub fn restore_state(&self, state: &GicDistributorState) {
+        let max_irqs = state.max_irqs.min(self.max_irqs());
+        let regs = self.regs();
+        regs.CTLR.set(0);
+        for i in SPI_RANGE.start / 32..max_irqs / 32 {
+            regs.ICENABLER[i].set(u32::MAX);
+            regs.IGROUPR[i].set(state.igroupr[i]);
+        }
+        for i in SPI_RANGE.start / 4..max_irqs / 4 {
+            regs.IPRIORITYR[i].set(state.ipriorityr[i]);
+            regs.ITARGETSR[i].set(state.itargetsr[i]);
+        }
+        for i in SPI_RANGE.start / 16..max_irqs / 16 {
+            regs.ICFGR[i].set(state.icfgr[i]);
+        }
+        for i in SPI_RANGE.start / 32..max_irqs / 32 {
+            regs.ISENABLER[i].set(state.isenabler[i]);
+        }
+        regs.CTLR.set(state.ctlr);
+    }
+
+    /// Saves the distributor registers banked for the calling CPU, i.e. the
+    /// SGI and PPI configuration, for system suspend.
+    pub fn save_banked_state(&self) -> GicDistributorBankedState {
+        let regs = self.regs();
+        let mut state = GicDistributorBankedState {
+            igroupr0: regs.IGROUPR[0].get(),
+            isenabler0: regs.ISENABLER[0].get(),
+            ipriorityr: [0; 8],
+            icfgr1: regs.ICFGR[1].get(),
+        };
+        for (i, val) in state.ipriorityr.iter_mut().enumerate() {
+            *val = regs.IPRIORITYR[i].get();
+        }
+        state
+    }
+
+    /// Restores the distributor registers banked for the calling CPU, saved
+    /// by [`Self::save_banked_state`], after system resume.
+    pub fn restore_banked_state(&self, state: &GicDistributorBankedState) {
+        let regs = self.regs();
+        regs.ICENABLER[0].set(u32::MAX);
+        regs.IGROUPR[0].set(state.igroupr0);
+        for (i, val) in state.ipriorityr.iter().enumerate() {
+            regs.IPRIORITYR[i].set(*val);
+        }
+        regs.ICFGR[1].set(state.icfgr1);
+        regs.ISENABLER[0].set(state.isenabler0);
+    }
+
     /// Initializes the GIC distributor.
     ///
     /// It disables all interrupts, sets the target of all SPIs to CPU 0,
     /// configures all SPIs to be edge-triggered, and finally enables the GICD.
+    /// The SGIs and PPIs of the calling CPU are initialized by
+    /// [`Self::init_cpu`].
     ///
     /// This function should be called only once.
     pub fn init(&mut self) {
+        self.init_with_config(&GicDistributorConfig::default());
+    }
+
+    /// Initializes the GIC distributor with the given configuration.
+    ///
+    /// Like [`Self::init`], but the trigger mode, priority, target and group
+    /// of the SPIs are taken from `config`. If
+    /// [`preserve_firmware_config`](GicDistributorConfig::preserve_firmware_config)
+    /// is set, the SPIs and the boot CPU's SGIs and PPIs are only disabled,
+    /// and keep the rest of their configuration.
+    ///
+    /// The quirks of the model identified from GICD_IIDR are looked up in
+    /// [`GIC_QUIRKS`](crate::GIC_QUIRKS), and added to the
+    /// [`cpu_interface_quirks`](GicDistributorConfig::cpu_interface_quirks).
+    ///
+    /// This function should be called only once.
+    pub fn init_with_config(&mut self, config: &GicDistributorConfig) {
         let max_irqs = self.max_irqs();
         assert!(max_irqs <= GIC_MAX_IRQ);
         self.max_irqs = max_irqs;
+        self.quirks |= quirks_of(self.model()) | config.cpu_interface_quirks;
+        self.probe_priority_bits();
 
-        // Disable all interrupts
-        for i in (0..max_irqs).step_by(32) {
+        self.init_banked(config.preserve_firmware_config);
+
+        // Disable all SPIs
+        for i in (SPI_RANGE.start..max_irqs).step_by(32) {
             self.regs().ICENABLER[i / 32].set(u32::MAX);
             self.regs().ICPENDR[i / 32].set(u32::MAX);
         }
-        if self.cpu_num() > 1 {
-            for i in (SPI_RANGE.start..max_irqs).step_by(4) {
-                // Set external interrupts to target cpu 0
-                self.regs().ITARGETSR[i / 4].set(0x01_01_01_01);
+        self.probe_implemented_irqs();
+        if !config.preserve_firmware_config {
+            if self.cpu_num() > 1 {
+                for i in (SPI_RANGE.start..max_irqs).step_by(4) {
+                    // Set external interrupts to the target cpus
+                    self.regs().ITARGETSR[i / 4].set(config.target_mask as u32 * 0x01_01_01_01);
+                }
+            }
+            if let Some(priority) = config.priority {
+                for i in (SPI_RANGE.start..max_irqs).step_by(4) {
+                    self.regs().IPRIORITYR[i / 4].set(priority as u32 * 0x01_01_01_01);
+                }
+            }
+            if let Some(group) = config.group {
+                for i in (SPI_RANGE.start..max_irqs).step_by(32) {
+                    self.regs().IGROUPR[i / 32].set(match group {
+                        InterruptGroup::Group0 => 0,
+                        InterruptGroup::Group1 => u32::MAX,
+                    });
+                }
+            }
+            // Initialize all the SPIs to the trigger mode
+            for i in SPI_RANGE.start..max_irqs {
+                self.configure_interrupt(i, config.trigger_mode);
             }
-        }
-        // Initialize all the SPIs to edge triggered
-        for i in SPI_RANGE.start..max_irqs {
-            self.configure_interrupt(i, TriggerMode::Edge);
         }
 
         // enable GIC0
@@ -371,13 +1022,60 @@ impl GicDistributor {
             .CTLR
             .set(self.regs().CTLR.get() | GICD_CTLR_EN_BIT);
     }
+
+    /// Initializes the distributor registers banked for the calling CPU.
+    ///
+    /// It disables all SGIs and PPIs of the calling CPU, clears their pending
+    /// and active state, sets their priority to [`GIC_DEFAULT_PRIORITY`], and
+    /// configures the PPIs whose trigger mode is programmable to be
+    /// level-sensitive.
+    ///
+    /// Only GICD_ICENABLER0, GICD_ICPENDR0, GICD_ICACTIVER0,
+    /// GICD_IPRIORITYR0-7 and GICD_ICFGR1 are written, so the SPI state is
+    /// never touched. It is called by [`Self::init`] for the boot CPU, and
+    /// should be called by each secondary CPU when it comes online.
+    pub fn init_cpu(&self) {
+        self.init_banked(false);
+    }
+
+    /// Initializes the banked registers, keeping the priority and trigger
+    /// mode of the SGIs and PPIs if `preserve` is set.
+    fn init_banked(&self, preserve: bool) {
+        let regs = self.regs();
+        regs.ICENABLER[0].set(u32::MAX);
+        regs.ICPENDR[0].set(u32::MAX);
+        regs.ICACTIVER[0].set(u32::MAX);
+        if preserve {
+            return;
+        }
+        let priority = GIC_DEFAULT_PRIORITY as u32 * 0x01_01_01_01;
+        for i in 0..PPI_RANGE.end / 4 {
+            regs.IPRIORITYR[i].set(priority);
+        }
+        regs.ICFGR[PPI_RANGE.start / 16].set(0);
+    }
 }
 
 impl GicCpuInterface {
     /// Construct a new GIC CPU interface instance from the base address.
     pub const fn new(base: *mut u8) -> Self {
+        Self::new_with_layout(base, GicCpuInterfaceLayout::Contiguous)
+    }
+
+    /// Construct a new GIC CPU interface instance from the base address, with
+    /// the given layout of its pages.
+    pub const fn new_with_layout(base: *mut u8, layout: GicCpuInterfaceLayout) -> Self {
+        let (base, dir_base) = match layout {
+            GicCpuInterfaceLayout::Contiguous => (base, base.wrapping_add(GICC_DIR_PAGE_OFFSET)),
+            GicCpuInterfaceLayout::Aliased64K => {
+                let base = base.wrapping_add(GICC_ALIAS_64K_OFFSET);
+                (base, base.wrapping_add(GICC_DIR_PAGE_OFFSET))
+            }
+            GicCpuInterfaceLayout::Split(dir_base) => (base, dir_base),
+        };
         Self {
             base: NonNull::new(base).unwrap().cast(),
+            dir_base: NonNull::new(dir_base).unwrap().cast(),
         }
     }
 
@@ -385,6 +1083,10 @@ impl GicCpuInterface {
         unsafe { self.base.as_ref() }
     }
 
+    const fn dir_regs(&self) -> &GicCpuInterfaceDirRegs {
+        unsafe { self.dir_base.as_ref() }
+    }
+
     /// Returns the interrupt ID of the highest priority pending interrupt for
     /// the CPU interface. (read GICC_IAR)
     ///
@@ -400,7 +1102,7 @@ impl GicCpuInterface {
     ///
     /// The value written must be the value returns from [`Self::iar`].
     ///
-    /// Note: with "el2" enabled, `GICC_CTLR`'s `GICC_CTLR_EOIMODENS_BIT` is set, which means
+    /// Note: in [`EoiMode::Split`], `GICC_CTLR`'s `GICC_CTLR_EOIMODENS_BIT` is set, which means
     /// the GICC_EOIR register has priority drop functionality only, to complete the processing
     /// of the specified interrupt, `dir` should be called to deactivate the interrupt.
     pub fn eoi(&self, iar: u32) {
@@ -414,7 +1116,27 @@ impl GicCpuInterface {
     ///
     /// The value written must be the value returns from [`Self::iar`].
     pub fn dir(&self, iar: u32) {
-        self.regs().DIR.set(iar);
+        self.dir_regs().DIR.set(iar);
+    }
+
+    /// Get the decoded iidr register. (read GICC_IIDR)
+    pub fn iidr(&self) -> GiccIidr {
+        GiccIidr::from_raw(self.regs().IIDR.get())
+    }
+
+    /// Identifies the GIC implementation from GICC_IIDR.
+    pub fn model(&self) -> GicModel {
+        GicModel::from_gicc_iidr(self.iidr())
+    }
+
+    /// The quirks of the implementation, looked up in
+    /// [`GIC_QUIRKS`](crate::GIC_QUIRKS).
+    pub fn quirks(&self) -> GicQuirks {
+        let mut quirks = quirks_of(self.model());
+        if self.iidr().arch_version == 1 {
+            quirks |= GicQuirks::NO_SPLIT_EOI;
+        }
+        quirks
     }
 
     /// Controls the CPU interface, including enabling of interrupt groups,
@@ -432,11 +1154,51 @@ impl GicCpuInterface {
         self.regs().CTLR.set(ctlr);
     }
 
+    /// Get the binary point. (read GICC_BPR)
+    ///
+    /// The binary point splits interrupt priorities into a group priority,
+    /// which determines preemption, and a subpriority.
+    pub fn binary_point(&self) -> u8 {
+        (self.regs().BPR.get() & 0b111) as u8
+    }
+
+    /// Set the binary point. (write GICC_BPR)
+    pub fn set_binary_point(&self, bpr: u8) {
+        self.regs().BPR.set((bpr & 0b111) as u32);
+    }
+
+    /// Returns the group priority of the given priority, per the binary point.
+    ///
+    /// An interrupt can only preempt the running one if its group priority is
+    /// higher (lower value) than the running priority.
+    pub fn group_priority(&self, priority: u8) -> u8 {
+        priority & (0xfe << self.binary_point())
+    }
+
+    /// Get the running priority of the CPU interface. (read GICC_RPR)
+    ///
+    /// It is `0xff` when no interrupt is active.
+    pub fn running_priority(&self) -> u8 {
+        (self.regs().RPR.get() & 0xff) as u8
+    }
+
+    /// Returns the EOI mode of the current CPU, read from GICC_CTLR.EOImodeNS.
+    ///
+    /// GICC_CTLR is banked, so it is read on every call rather than cached.
+    pub fn eoi_mode(&self) -> EoiMode {
+        if self.regs().CTLR.get() & GICC_CTLR_EOIMODENS_BIT != 0 {
+            EoiMode::Split
+        } else {
+            EoiMode::Combined
+        }
+    }
+
     /// handles the signaled interrupt.
     ///
     /// It first reads GICC_IAR to obtain the pending interrupt ID and then
     /// calls the given handler. After the handler returns, it writes GICC_EOIR
-    /// to acknowledge the interrupt.
+    /// to acknowledge the interrupt, and also writes GICC_DIR to deactivate it
+    /// if the CPU interface is in [`EoiMode::Split`].
     ///
     /// If read GICC_IAR returns a spurious interrupt ID of `1023`, it does
     /// nothing.
@@ -448,32 +1210,158 @@ impl GicCpuInterface {
         let vector = iar & 0x3ff;
         if vector < 1020 {
             handler(vector);
-            self.eoi(iar);
-            #[cfg(feature = "el2")]
-            if self.regs().CTLR.get() & GICC_CTLR_EOIMODENS_BIT != 0 {
-                self.dir(iar);
-            }
+            self.complete(iar);
+        } else {
+            // spurious
+        }
+    }
+
+    /// Handles the signaled interrupt with the handler registered in `table`.
+    ///
+    /// Like [`Self::handle_irq`], but calls the handler registered for the
+    /// interrupt on CPU `cpu_id`. If no handler is registered, the interrupt
+    /// is reported as [`IrqDispatch::Unhandled`], and disabled in `gicd` if it
+    /// is given, so that it does not fire again.
+    pub fn dispatch_irq<const CPUS: usize>(
+        &self,
+        table: &IrqHandlerTable<CPUS>,
+        cpu_id: usize,
+        gicd: Option<&GicDistributor>,
+    ) -> IrqDispatch {
+        let mut result = IrqDispatch::Spurious;
+        self.handle_irq(|vector| {
+            result = if table.handle(cpu_id, vector) {
+                IrqDispatch::Handled(vector)
+            } else {
+                IrqDispatch::Unhandled(vector)
+            };
+        });
+        if let (IrqDispatch::Unhandled(vector), Some(gicd)) = (result, gicd) {
+            gicd.set_enable(vector as usize, false);
+        }
+        result
+    }
+
+    /// Handles the signaled interrupt, allowing it to be preempted.
+    ///
+    /// Like [`Self::handle_irq`], but the running priority after acknowledging
+    /// the interrupt is pushed to `state` while the handler runs. The handler
+    /// may re-enable interrupts on the CPU, so that interrupts with a higher
+    /// group priority (see [`Self::group_priority`]) preempt it and are
+    /// handled by a nested call. It must disable them again before returning,
+    /// as the interrupt is completed afterwards.
+    pub fn handle_irq_nested<const N: usize, F>(&self, state: &IrqNestState<N>, handler: F)
+    where
+        F: FnOnce(u32),
+    {
+        let iar = self.iar();
+        let vector = iar & 0x3ff;
+        if vector < 1020 {
+            state.push(self.running_priority());
+            handler(vector);
+            state.pop();
+            self.complete(iar);
         } else {
             // spurious
         }
     }
 
+    /// Handles all the signaled interrupts.
+    ///
+    /// Like [`Self::handle_irq`], but keeps reading GICC_IAR and calling the
+    /// given handler until a spurious interrupt ID is returned, or `max_batch`
+    /// interrupts have been handled. This saves the exception entry cost of
+    /// the interrupts that become pending while another one is handled.
+    ///
+    /// Returns the number of interrupts handled.
+    pub fn handle_pending_irqs<F>(&self, max_batch: usize, mut handler: F) -> usize
+    where
+        F: FnMut(u32),
+    {
+        let mut handled = 0;
+        while handled < max_batch {
+            let iar = self.iar();
+            let vector = iar & 0x3ff;
+            if vector >= 1020 {
+                // spurious, no more pending interrupts
+                break;
+            }
+            handler(vector);
+            self.complete(iar);
+            handled += 1;
+        }
+        handled
+    }
+
+    /// Completes the processing of an acknowledged interrupt.
+    ///
+    /// It writes GICC_EOIR, and also GICC_DIR in [`EoiMode::Split`].
+    pub(crate) fn complete(&self, iar: u32) {
+        self.eoi(iar);
+        if self.eoi_mode() == EoiMode::Split {
+            self.dir(iar);
+        }
+    }
+
+    /// Saves the CPU interface registers, for system suspend.
+    pub fn save_state(&self) -> GicCpuInterfaceState {
+        let regs = self.regs();
+        let mut state = GicCpuInterfaceState {
+            ctlr: regs.CTLR.get(),
+            pmr: regs.PMR.get(),
+            bpr: regs.BPR.get(),
+            abpr: regs.ABPR.get(),
+            apr: [0; 4],
+            nsapr: [0; 4],
+        };
+        for i in 0..4 {
+            state.apr[i] = regs.APR[i].get();
+            state.nsapr[i] = regs.NSAPR[i].get();
+        }
+        state
+    }
+
+    /// Restores the CPU interface registers saved by [`Self::save_state`],
+    /// after system resume.
+    ///
+    /// GICC_CTLR is restored last, which enables the CPU interface again if
+    /// it was enabled.
+    pub fn restore_state(&self, state: &GicCpuInterfaceState) {
+        let regs = self.regs();
+        regs.PMR.set(state.pmr);
+        regs.BPR.set(state.bpr);
+        regs.ABPR.set(state.abpr);
+        for i in 0..4 {
+            regs.APR[i].set(state.apr[i]);
+            regs.NSAPR[i].set(state.nsapr[i]);
+        }
+        self.set_ctlr(state.ctlr);
+    }
+
     /// Initializes the GIC CPU interface.
     ///
-    /// It unmask interrupts at all priority levels and enables the GICC.
+    /// It unmask interrupts at all priority levels and enables the GICC, with
+    /// the [default](GicCpuInterfaceConfig::default) configuration.
     ///
     /// This function should be called only once.
     pub fn init(&self) {
-        #[cfg(not(feature = "el2"))]
-        self.regs().CTLR.set(GICC_CTLR_EN_BIT);
+        self.init_with_config(&GicCpuInterfaceConfig::default());
+    }
 
-        #[cfg(feature = "el2")]
-        {
-            self.regs()
-                .CTLR
-                .set(GICC_CTLR_EN_BIT | GICC_CTLR_EOIMODENS_BIT);
+    /// Initializes the GIC CPU interface with the given configuration.
+    ///
+    /// It sets the priority mask, then programs GICC_CTLR with the selected
+    /// EOI mode, group enables and bypass behaviour. [`EoiMode::Split`] falls
+    /// back to [`EoiMode::Combined`] if the CPU interface does not support it
+    /// (see [`GicQuirks::NO_SPLIT_EOI`]).
+    ///
+    /// This function should be called only once per CPU.
+    pub fn init_with_config(&self, config: &GicCpuInterfaceConfig) {
+        let mut config = *config;
+        if self.quirks().contains(GicQuirks::NO_SPLIT_EOI) {
+            config.eoi_mode = EoiMode::Combined;
         }
-        // unmask interrupts at all priority levels
-        self.regs().PMR.set(u32::MAX);
+        self.regs().PMR.set(config.priority_mask as u32);
+        self.set_ctlr(config.ctlr());
     }
 }
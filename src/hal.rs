@@ -0,0 +1,118 @@
+//! Glue for the IRQ layer of ArceOS's `axhal`.
+//!
+//! The distributor, the CPU interface and the handler table are kept in
+//! globals, so a platform only has to call [`init_primary`] and
+//! [`init_secondary`], and forward its IRQ functions here.
+
+use spin::Once;
+
+use crate::{
+    GicCpuInterface, GicDistributor, GicDistributorConfig, IrqDispatch, IrqHandler,
+    IrqHandlerTable, GIC_MAX_CPUS, PPI_RANGE,
+};
+
+static GICD: Once<GicDistributor> = Once::new();
+
+static GICC: Once<GicCpuInterface> = Once::new();
+
+static HANDLERS: IrqHandlerTable<GIC_MAX_CPUS> = IrqHandlerTable::new();
+
+/// Returns the global distributor.
+///
+/// # Panics
+///
+/// Panics if [`init_primary`] has not been called.
+pub fn distributor() -> &'static GicDistributor {
+    GICD.get().expect("GIC is not initialized")
+}
+
+/// Returns the global CPU interface. Its registers are banked, so it is the
+/// interface of the current CPU.
+///
+/// # Panics
+///
+/// Panics if [`init_primary`] has not been called.
+pub fn cpu_interface() -> &'static GicCpuInterface {
+    GICC.get().expect("GIC is not initialized")
+}
+
+/// Initializes the distributor and CPU interface, and makes them global, on
+/// the primary CPU with logical ID `cpu_id`.
+///
+/// Later calls do nothing.
+pub fn init_primary(cpu_id: usize, mut gicd: GicDistributor, gicc: GicCpuInterface) {
+    GICD.call_once(|| {
+        gicd.init_with_config(&GicDistributorConfig {
+            cpu_interface_quirks: gicc.quirks(),
+            ..Default::default()
+        });
+        gicd
+    })
+    .register_current_cpu(cpu_id);
+    GICC.call_once(|| {
+        gicc.init();
+        gicc
+    });
+}
+
+/// Initializes the banked distributor registers and the CPU interface of a
+/// secondary CPU with logical ID `cpu_id`.
+pub fn init_secondary(cpu_id: usize) {
+    let gicd = distributor();
+    gicd.init_cpu();
+    gicd.register_current_cpu(cpu_id);
+    cpu_interface().init();
+}
+
+/// Enables or disables the given interrupt.
+///
+/// SGIs and PPIs are only enabled or disabled on the current CPU.
+pub fn set_enable(irq: usize, enabled: bool) {
+    distributor().set_enable(irq, enabled);
+}
+
+/// Registers the handler of the given interrupt, and enables it.
+///
+/// The handler of an SGI or PPI is registered for all CPUs, but the
+/// interrupt is only enabled on the current one.
+///
+/// Returns `false` if the interrupt is invalid or a handler is already
+/// registered, on any CPU for an SGI or PPI. Nothing is registered then.
+pub fn register_handler(irq: usize, handler: IrqHandler) -> bool {
+    let registered = if irq < PPI_RANGE.end {
+        match (0..GIC_MAX_CPUS).find(|&cpu_id| !HANDLERS.register_local(cpu_id, irq, handler)) {
+            Some(failed) => {
+                // roll back the CPUs registered so far
+                for cpu_id in 0..failed {
+                    HANDLERS.unregister_local(cpu_id, irq);
+                }
+                false
+            }
+            None => true,
+        }
+    } else {
+        HANDLERS.register_shared(irq, handler)
+    };
+    if registered {
+        set_enable(irq, true);
+    }
+    registered
+}
+
+/// Disables the given interrupt, and unregisters its handler.
+pub fn unregister_handler(irq: usize) -> Option<IrqHandler> {
+    set_enable(irq, false);
+    if irq < PPI_RANGE.end {
+        (0..GIC_MAX_CPUS).fold(None, |handler, cpu_id| {
+            HANDLERS.unregister_local(cpu_id, irq).or(handler)
+        })
+    } else {
+        HANDLERS.unregister_shared(irq)
+    }
+}
+
+/// Handles the signaled interrupt on the CPU with logical ID `cpu_id`, see
+/// [`GicCpuInterface::dispatch_irq`]. An unhandled interrupt is disabled.
+pub fn dispatch_irq(cpu_id: usize) -> IrqDispatch {
+    cpu_interface().dispatch_irq(&HANDLERS, cpu_id, Some(distributor()))
+}
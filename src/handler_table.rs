@@ -0,0 +1,377 @@
+//! Interrupt handler registry and dispatch table.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{GicDistributor, PPI_RANGE, SGI_RANGE, SHARED_IRQ_NUM, SPI_RANGE};
+
+/// Interrupt handler, called with the ID of the interrupt being handled.
+pub type IrqHandler = fn(u32);
+
+/// Handler of an interrupt line shared by several devices, called with the ID
+/// of the interrupt being handled.
+///
+/// Returns `true` if its device raised the interrupt and it has been handled.
+pub type SharedIrqHandler = fn(u32) -> bool;
+
+/// Number of banked interrupts (SGIs and PPIs) of each CPU.
+const LOCAL_IRQ_NUM: usize = PPI_RANGE.end;
+
+/// The result of dispatching an interrupt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqDispatch {
+    /// The interrupt with the given ID was handled by a registered handler.
+    Handled(u32),
+    /// No handler is registered for the interrupt with the given ID.
+    Unhandled(u32),
+    /// GICC_IAR returned a spurious interrupt ID.
+    Spurious,
+}
+
+/// A table of interrupt handlers keyed by interrupt ID.
+///
+/// SGIs and PPIs are banked per CPU, so each of the `CPUS` CPUs has its own
+/// handlers for them. SPIs share a single handler between all CPUs.
+///
+/// Registering, unregistering and dispatching are lock-free, so the table can
+/// be placed in a `static` and used from interrupt context.
+pub struct IrqHandlerTable<const CPUS: usize> {
+    local: [[AtomicUsize; LOCAL_IRQ_NUM]; CPUS],
+    shared: [AtomicUsize; SHARED_IRQ_NUM],
+}
+
+impl<const CPUS: usize> IrqHandlerTable<CPUS> {
+    /// Creates a new table with no handlers registered.
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        Self {
+            local: [const { [const { AtomicUsize::new(0) }; LOCAL_IRQ_NUM] }; CPUS],
+            shared: [const { AtomicUsize::new(0) }; SHARED_IRQ_NUM],
+        }
+    }
+
+    fn slot(&self, cpu_id: usize, irq: usize) -> Option<&AtomicUsize> {
+        if SGI_RANGE.contains(&irq) || PPI_RANGE.contains(&irq) {
+            self.local.get(cpu_id).map(|local| &local[irq])
+        } else if SPI_RANGE.contains(&irq) {
+            Some(&self.shared[irq - SPI_RANGE.start])
+        } else {
+            None
+        }
+    }
+
+    fn register_slot(slot: Option<&AtomicUsize>, handler: IrqHandler) -> bool {
+        slot.is_some_and(|slot| {
+            slot.compare_exchange(0, handler as usize, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        })
+    }
+
+    fn unregister_slot(slot: Option<&AtomicUsize>) -> Option<IrqHandler> {
+        match slot?.swap(0, Ordering::AcqRel) {
+            0 => None,
+            // SAFETY: only `IrqHandler`s are stored in the table.
+            handler => Some(unsafe { core::mem::transmute::<usize, IrqHandler>(handler) }),
+        }
+    }
+
+    /// Registers the handler of an SGI or PPI for the given CPU.
+    ///
+    /// Returns `false` if the interrupt is not an SGI or PPI, the CPU ID is
+    /// out of range, or a handler is already registered.
+    pub fn register_local(&self, cpu_id: usize, irq: usize, handler: IrqHandler) -> bool {
+        if irq >= LOCAL_IRQ_NUM {
+            return false;
+        }
+        Self::register_slot(self.slot(cpu_id, irq), handler)
+    }
+
+    /// Registers the handler of an SPI, shared by all CPUs.
+    ///
+    /// Returns `false` if the interrupt is not an SPI, or a handler is
+    /// already registered.
+    pub fn register_shared(&self, irq: usize, handler: IrqHandler) -> bool {
+        if !SPI_RANGE.contains(&irq) {
+            return false;
+        }
+        Self::register_slot(self.slot(0, irq), handler)
+    }
+
+    /// Unregisters the handler of an SGI or PPI for the given CPU, and
+    /// returns it.
+    pub fn unregister_local(&self, cpu_id: usize, irq: usize) -> Option<IrqHandler> {
+        if irq >= LOCAL_IRQ_NUM {
+            return None;
+        }
+        Self::unregister_slot(self.slot(cpu_id, irq))
+    }
+
+    /// Unregisters the handler of an SPI, and returns it.
+    pub fn unregister_shared(&self, irq: usize) -> Option<IrqHandler> {
+        if !SPI_RANGE.contains(&irq) {
+            return None;
+        }
+        Self::unregister_slot(self.slot(0, irq))
+    }
+
+    /// Returns the handler that the given CPU calls for the given interrupt.
+    pub fn handler(&self, cpu_id: usize, irq: usize) -> Option<IrqHandler> {
+        match self.slot(cpu_id, irq)?.load(Ordering::Acquire) {
+            0 => None,
+            // SAFETY: only `IrqHandler`s are stored in the table.
+            handler => Some(unsafe { core::mem::transmute::<usize, IrqHandler>(handler) }),
+        }
+    }
+
+    /// Calls the handler of the given interrupt on the given CPU.
+    ///
+    /// Returns `false` if no handler is registered.
+    pub fn handle(&self, cpu_id: usize, irq: u32) -> bool {
+        if let Some(handler) = self.handler(cpu_id, irq as usize) {
+            handler(irq);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A chain of handlers for an interrupt line shared by up to `N` devices.
+///
+/// When the interrupt fires, all the handlers are called in turn, since
+/// several devices may raise the line at the same time. If none of them
+/// claims the interrupt `max_unclaimed` times in a row, the line is considered
+/// stuck and is disabled in the distributor.
+///
+/// Like [`IrqHandlerTable`], it is lock-free and can be placed in a `static`.
+/// Its [`handle`](Self::handle) method is usually called from the
+/// [`IrqHandler`] registered for the line.
+pub struct SharedIrqLine<const N: usize> {
+    handlers: [AtomicUsize; N],
+    unclaimed: AtomicUsize,
+    max_unclaimed: usize,
+}
+
+impl<const N: usize> SharedIrqLine<N> {
+    /// Creates a new line with no handlers, which is disabled after
+    /// `max_unclaimed` consecutive unclaimed interrupts.
+    pub const fn new(max_unclaimed: usize) -> Self {
+        Self {
+            handlers: [const { AtomicUsize::new(0) }; N],
+            unclaimed: AtomicUsize::new(0),
+            max_unclaimed,
+        }
+    }
+
+    /// Adds a handler to the chain.
+    ///
+    /// Returns `false` if the chain is full.
+    pub fn add_handler(&self, handler: SharedIrqHandler) -> bool {
+        self.handlers.iter().any(|slot| {
+            slot.compare_exchange(0, handler as usize, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        })
+    }
+
+    /// Removes a handler from the chain.
+    ///
+    /// Returns `false` if the handler is not in the chain.
+    pub fn remove_handler(&self, handler: SharedIrqHandler) -> bool {
+        self.handlers.iter().any(|slot| {
+            slot.compare_exchange(handler as usize, 0, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        })
+    }
+
+    /// Calls all the handlers of the chain for the given interrupt.
+    ///
+    /// Returns `true` if at least one handler claimed the interrupt. Otherwise
+    /// the unclaimed count is increased, and once it reaches `max_unclaimed`,
+    /// the interrupt is disabled in `gicd` if it is given.
+    pub fn handle(&self, irq: u32, gicd: Option<&GicDistributor>) -> bool {
+        let mut claimed = false;
+        for slot in &self.handlers {
+            let handler = slot.load(Ordering::Acquire);
+            if handler != 0 {
+                // SAFETY: only `SharedIrqHandler`s are stored in the chain.
+                let handler = unsafe { core::mem::transmute::<usize, SharedIrqHandler>(handler) };
+                claimed |= handler(irq);
+            }
+        }
+
+        if claimed {
+            self.unclaimed.store(0, Ordering::Relaxed);
+        } else if self.unclaimed.fetch_add(1, Ordering::Relaxed) + 1 >= self.max_unclaimed {
+            if let Some(gicd) = gicd {
+                gicd.set_enable(irq as usize, false);
+                self.unclaimed.store(0, Ordering::Relaxed);
+            }
+        }
+        claimed
+    }
+
+    /// Returns the number of consecutive interrupts that no handler claimed.
+    pub fn unclaimed_count(&self) -> usize {
+        self.unclaimed.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::AtomicU32;
+
+    use super::*;
+
+    static LAST_A: AtomicU32 = AtomicU32::new(0);
+    static LAST_B: AtomicU32 = AtomicU32::new(0);
+
+    // Distinct bodies, so that the two functions are not merged.
+    fn handler_a(irq: u32) {
+        LAST_A.store(irq, Ordering::Relaxed);
+    }
+
+    fn handler_b(irq: u32) {
+        LAST_B.store(irq, Ordering::Relaxed);
+    }
+
+    /// The address of a handler, as function pointers cannot be compared.
+    fn addr(handler: Option<IrqHandler>) -> Option<usize> {
+        handler.map(|handler| handler as usize)
+    }
+
+    #[test]
+    fn register_and_unregister() {
+        let table = IrqHandlerTable::<2>::new();
+        assert!(table.register_shared(40, handler_a));
+        // a handler is already registered
+        assert!(!table.register_shared(40, handler_b));
+        assert_eq!(addr(table.handler(0, 40)), addr(Some(handler_a)));
+        assert_eq!(addr(table.handler(1, 40)), addr(Some(handler_a)));
+
+        assert_eq!(addr(table.unregister_shared(40)), addr(Some(handler_a)));
+        assert_eq!(addr(table.unregister_shared(40)), None);
+        assert_eq!(addr(table.handler(0, 40)), None);
+        assert!(table.register_shared(40, handler_b));
+        assert_eq!(addr(table.handler(0, 40)), addr(Some(handler_b)));
+    }
+
+    #[test]
+    fn local_handlers_are_banked() {
+        let table = IrqHandlerTable::<2>::new();
+        assert!(table.register_local(0, 1, handler_a));
+        assert!(table.register_local(1, 1, handler_b));
+        assert!(table.register_local(1, 27, handler_a));
+        assert_eq!(addr(table.handler(0, 1)), addr(Some(handler_a)));
+        assert_eq!(addr(table.handler(1, 1)), addr(Some(handler_b)));
+        assert_eq!(addr(table.handler(0, 27)), None);
+
+        assert_eq!(addr(table.unregister_local(0, 1)), addr(Some(handler_a)));
+        assert_eq!(addr(table.handler(0, 1)), None);
+        assert_eq!(addr(table.handler(1, 1)), addr(Some(handler_b)));
+    }
+
+    #[test]
+    fn reject_invalid_irqs() {
+        let table = IrqHandlerTable::<2>::new();
+        // out of range CPU
+        assert!(!table.register_local(2, 1, handler_a));
+        assert_eq!(addr(table.unregister_local(2, 1)), None);
+        assert_eq!(addr(table.handler(2, 1)), None);
+        // SPIs are not local, SGIs and PPIs are not shared
+        assert!(!table.register_local(0, 32, handler_a));
+        assert!(!table.register_shared(31, handler_a));
+        // special and out of range IDs
+        for irq in [1020, 1023, 1024, usize::MAX] {
+            assert!(!table.register_shared(irq, handler_a));
+            assert_eq!(addr(table.unregister_shared(irq)), None);
+            assert_eq!(addr(table.handler(0, irq)), None);
+            assert!(!table.handle(0, irq as u32));
+        }
+    }
+
+    #[test]
+    fn handle_calls_the_handler() {
+        static HANDLED: AtomicU32 = AtomicU32::new(0);
+        fn handler(irq: u32) {
+            HANDLED.store(irq, Ordering::Relaxed);
+        }
+
+        let table = IrqHandlerTable::<2>::new();
+        assert!(table.register_local(1, 16, handler));
+        assert!(!table.handle(0, 16));
+        assert!(table.handle(1, 16));
+        assert_eq!(HANDLED.load(Ordering::Relaxed), 16);
+
+        assert!(!table.handle(0, 100));
+        assert!(table.register_shared(100, handler));
+        assert!(table.handle(0, 100));
+        assert_eq!(HANDLED.load(Ordering::Relaxed), 100);
+    }
+
+    #[test]
+    fn shared_line_claims() {
+        static CLAIMS: AtomicU32 = AtomicU32::new(0);
+        fn claiming(_irq: u32) -> bool {
+            CLAIMS.fetch_add(1, Ordering::Relaxed);
+            true
+        }
+        fn unclaiming(_irq: u32) -> bool {
+            false
+        }
+
+        let line = SharedIrqLine::<2>::new(3);
+        assert!(line.add_handler(unclaiming));
+        assert!(!line.handle(40, None));
+        assert!(!line.handle(40, None));
+        assert_eq!(line.unclaimed_count(), 2);
+
+        // a claim resets the count, and all the handlers are called
+        assert!(line.add_handler(claiming));
+        assert!(!line.add_handler(claiming));
+        assert!(line.handle(40, None));
+        assert_eq!(line.unclaimed_count(), 0);
+        assert_eq!(CLAIMS.load(Ordering::Relaxed), 1);
+
+        assert!(line.remove_handler(claiming));
+        assert!(!line.remove_handler(claiming));
+        assert!(!line.handle(40, None));
+        assert_eq!(line.unclaimed_count(), 1);
+        assert_eq!(CLAIMS.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn shared_line_threshold() {
+        /// A distributor backed by memory, to observe the disabling of the
+        /// line.
+        #[repr(align(4096))]
+        struct Regs([u32; 0x400]);
+
+        fn unclaiming(_irq: u32) -> bool {
+            false
+        }
+
+        let line = SharedIrqLine::<1>::new(3);
+        assert!(line.add_handler(unclaiming));
+        // without a distributor, the count keeps growing past the threshold
+        for count in 1..=4 {
+            assert!(!line.handle(40, None));
+            assert_eq!(line.unclaimed_count(), count);
+        }
+
+        let line = SharedIrqLine::<1>::new(3);
+        assert!(line.add_handler(unclaiming));
+        let mut regs = Regs([0; 0x400]);
+        let base = regs.0.as_mut_ptr();
+        let gicd = GicDistributor::new(base.cast());
+        // SAFETY: GICD_ICENABLER1 is within `regs`.
+        let icenabler1 = || unsafe { base.add(0x184 / 4).read_volatile() };
+        for _ in 0..2 {
+            assert!(!line.handle(40, Some(&gicd)));
+        }
+        assert_eq!(line.unclaimed_count(), 2);
+        assert_eq!(icenabler1(), 0);
+        // the line is disabled at the threshold, and the count reset
+        assert!(!line.handle(40, Some(&gicd)));
+        assert_eq!(line.unclaimed_count(), 0);
+        assert_eq!(icenabler1(), 1 << (40 - 32));
+    }
+}
@@ -0,0 +1,160 @@
+//! Implementer identification (GICD_IIDR and GICC_IIDR).
+
+/// JEP106 code of Arm, in the implementer field of the IIDR registers.
+pub const IIDR_IMPLEMENTER_ARM: u16 = 0x43b;
+
+/// Decoded Distributor Implementer Identification Register, GICD_IIDR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GicdIidr {
+    /// Bits 31:24, ProductID, an IMPLEMENTATION DEFINED product identifier.
+    pub product_id: u8,
+    /// Bits 19:16, Variant, usually the major revision number of the product.
+    pub variant: u8,
+    /// Bits 15:12, Revision, usually the minor revision number of the product.
+    pub revision: u8,
+    /// Bits 11:0, Implementer, the JEP106 code of the implementer.
+    pub implementer: u16,
+}
+
+impl GicdIidr {
+    /// Decodes a raw GICD_IIDR value.
+    pub const fn from_raw(iidr: u32) -> Self {
+        Self {
+            product_id: (iidr >> 24) as u8,
+            variant: ((iidr >> 16) & 0xf) as u8,
+            revision: ((iidr >> 12) & 0xf) as u8,
+            implementer: (iidr & 0xfff) as u16,
+        }
+    }
+}
+
+/// Decoded CPU Interface Identification Register, GICC_IIDR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GiccIidr {
+    /// Bits 31:20, ProductID, an IMPLEMENTATION DEFINED product identifier.
+    pub product_id: u16,
+    /// Bits 19:16, architecture version of the CPU interface, `1` for GICv1 and
+    /// `2` for GICv2.
+    pub arch_version: u8,
+    /// Bits 15:12, Revision, usually the minor revision number of the product.
+    pub revision: u8,
+    /// Bits 11:0, Implementer, the JEP106 code of the implementer.
+    pub implementer: u16,
+}
+
+impl GiccIidr {
+    /// Decodes a raw GICC_IIDR value.
+    pub const fn from_raw(iidr: u32) -> Self {
+        Self {
+            product_id: (iidr >> 20) as u16,
+            arch_version: ((iidr >> 16) & 0xf) as u8,
+            revision: ((iidr >> 12) & 0xf) as u8,
+            implementer: (iidr & 0xfff) as u16,
+        }
+    }
+}
+
+/// Known GIC implementations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GicModel {
+    /// Arm CoreLink GIC-390 (PL390), a GICv1 implementation.
+    Gic390,
+    /// Arm CoreLink GIC-400.
+    Gic400,
+    /// The GIC emulated by QEMU, without KVM acceleration.
+    Qemu,
+    /// Any other implementation.
+    Unknown,
+}
+
+impl GicModel {
+    /// Identifies the implementation from its GICD_IIDR.
+    ///
+    /// Only the GIC-400 can be told apart this way, QEMU's emulated GIC
+    /// reading GICD_IIDR as zero.
+    pub const fn from_gicd_iidr(iidr: GicdIidr) -> Self {
+        match (iidr.implementer, iidr.product_id) {
+            (IIDR_IMPLEMENTER_ARM, 0x02) => Self::Gic400,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Identifies the implementation from its GICC_IIDR.
+    pub const fn from_gicc_iidr(iidr: GiccIidr) -> Self {
+        match (iidr.implementer, iidr.product_id) {
+            (IIDR_IMPLEMENTER_ARM, 0x390) => Self::Gic390,
+            (IIDR_IMPLEMENTER_ARM, 0x020) => Self::Gic400,
+            (IIDR_IMPLEMENTER_ARM, 0x000) => Self::Qemu,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{quirks_of, GicCpuInterface, GicQuirks};
+
+    #[test]
+    fn decode_gic400() {
+        let gicd = GicdIidr::from_raw(0x0200_143b);
+        assert_eq!(
+            gicd,
+            GicdIidr {
+                product_id: 0x02,
+                variant: 0,
+                revision: 1,
+                implementer: IIDR_IMPLEMENTER_ARM,
+            }
+        );
+        assert_eq!(GicModel::from_gicd_iidr(gicd), GicModel::Gic400);
+
+        let gicc = GiccIidr::from_raw(0x0202_143b);
+        assert_eq!(
+            gicc,
+            GiccIidr {
+                product_id: 0x020,
+                arch_version: 2,
+                revision: 1,
+                implementer: IIDR_IMPLEMENTER_ARM,
+            }
+        );
+        assert_eq!(GicModel::from_gicc_iidr(gicc), GicModel::Gic400);
+        assert_eq!(quirks_of(GicModel::Gic400), GicQuirks::NONE);
+    }
+
+    #[test]
+    fn decode_qemu() {
+        let gicc = GiccIidr::from_raw(0x0002_043b);
+        assert_eq!(gicc.product_id, 0);
+        assert_eq!(gicc.arch_version, 2);
+        assert_eq!(gicc.implementer, IIDR_IMPLEMENTER_ARM);
+        assert_eq!(GicModel::from_gicc_iidr(gicc), GicModel::Qemu);
+        // QEMU reads GICD_IIDR as zero
+        assert_eq!(
+            GicModel::from_gicd_iidr(GicdIidr::from_raw(0)),
+            GicModel::Unknown
+        );
+    }
+
+    #[test]
+    fn decode_gicv1() {
+        // PL390
+        let gicc = GiccIidr::from_raw(0x3901_243b);
+        assert_eq!(gicc.product_id, 0x390);
+        assert_eq!(gicc.arch_version, 1);
+        assert_eq!(gicc.revision, 2);
+        assert_eq!(GicModel::from_gicc_iidr(gicc), GicModel::Gic390);
+        assert!(quirks_of(GicModel::Gic390).contains(GicQuirks::NO_SPLIT_EOI));
+
+        // Any GICv1 CPU interface lacks GICC_DIR, known or not.
+        #[repr(align(4096))]
+        struct Regs([u32; 0x400]);
+        let mut regs = Regs([0; 0x400]);
+        // GICC_IIDR of an unknown implementer
+        regs.0[0xfc / 4] = 0x0001_1123;
+        let gicc = GicCpuInterface::new(regs.0.as_mut_ptr().cast());
+        assert_eq!(gicc.model(), GicModel::Unknown);
+        assert!(gicc.quirks().contains(GicQuirks::NO_SPLIT_EOI));
+    }
+}
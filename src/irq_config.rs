@@ -0,0 +1,139 @@
+//! Declarative static interrupt configuration.
+
+use crate::{GicDistributor, InterruptGroup, TriggerMode, SGI_RANGE, SPI_RANGE};
+
+/// Static configuration of an interrupt.
+///
+/// A platform can describe all its interrupts in a `const` table of
+/// `IrqConfig`s, and apply it with [`GicDistributor::apply_irq_configs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IrqConfig {
+    /// The interrupt ID.
+    pub id: usize,
+    /// The trigger mode. SGIs are always edge-triggered, and the trigger mode
+    /// of some PPIs is fixed.
+    pub trigger_mode: TriggerMode,
+    /// The priority.
+    pub priority: u8,
+    /// The CPU target mask. Only used for SPIs.
+    pub target_mask: u8,
+    /// The interrupt group.
+    pub group: InterruptGroup,
+    /// Whether the interrupt is enabled.
+    pub enabled: bool,
+}
+
+/// The reason why an [`IrqConfig`] could not be applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqConfigError {
+    /// The interrupt ID is not below [`GicDistributor::max_irqs`].
+    InvalidId,
+    /// The interrupt is not implemented, see [`GicDistributor::is_implemented`].
+    Unimplemented,
+    /// The CPU target mask of an SPI is empty, or names a CPU interface that
+    /// is not below [`GicDistributor::cpu_num`].
+    InvalidTarget,
+    /// The trigger mode is not supported by the interrupt, or is fixed to
+    /// the other mode.
+    InvalidTriggerMode,
+}
+
+/// The result of [`GicDistributor::apply_irq_configs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IrqConfigReport {
+    /// The number of applied entries.
+    pub applied: usize,
+    /// The number of rejected entries.
+    pub rejected: usize,
+}
+
+impl GicDistributor {
+    /// Checks whether the given interrupt configuration can be applied.
+    pub fn validate_irq_config(&self, config: &IrqConfig) -> Result<(), IrqConfigError> {
+        if config.id >= self.max_irqs().min(SPI_RANGE.end) {
+            return Err(IrqConfigError::InvalidId);
+        }
+        if !self.is_implemented(config.id) {
+            return Err(IrqConfigError::Unimplemented);
+        }
+        if SGI_RANGE.contains(&config.id) && config.trigger_mode != TriggerMode::Edge {
+            return Err(IrqConfigError::InvalidTriggerMode);
+        }
+        if SPI_RANGE.contains(&config.id)
+            && (config.target_mask == 0 || (config.target_mask as usize) >> self.cpu_num() != 0)
+        {
+            return Err(IrqConfigError::InvalidTarget);
+        }
+        Ok(())
+    }
+
+    /// Applies a table of interrupt configurations.
+    ///
+    /// Each valid entry is applied in turn: the interrupt is disabled, its
+    /// group, priority, target and trigger mode are programmed, and it is
+    /// enabled again if requested. Invalid entries are skipped. An entry whose
+    /// trigger mode does not read back as requested, because it is fixed by
+    /// the implementation, is rejected with
+    /// [`IrqConfigError::InvalidTriggerMode`] and its interrupt is left
+    /// disabled.
+    ///
+    /// The index in the table and the reason of each rejected entry are
+    /// written to `rejected`, as long as it has room. The entries that would
+    /// be rejected by validation alone can also be listed beforehand with
+    /// [`Self::rejected_irq_configs`].
+    ///
+    /// It should be called after [`Self::init`].
+    pub fn apply_irq_configs(
+        &self,
+        table: &[IrqConfig],
+        rejected: &mut [(usize, IrqConfigError)],
+    ) -> IrqConfigReport {
+        let mut report = IrqConfigReport {
+            applied: 0,
+            rejected: 0,
+        };
+        let mut reject = |report: &mut IrqConfigReport, i, err| {
+            if let Some(slot) = rejected.get_mut(report.rejected) {
+                *slot = (i, err);
+            }
+            report.rejected += 1;
+        };
+        for (i, config) in table.iter().enumerate() {
+            if let Err(err) = self.validate_irq_config(config) {
+                reject(&mut report, i, err);
+                continue;
+            }
+            self.set_enable(config.id, false);
+            self.set_group(config.id, config.group);
+            self.set_priority(config.id, config.priority);
+            if SPI_RANGE.contains(&config.id) {
+                self.set_target_cpu(config.id, config.target_mask);
+            }
+            self.configure_interrupt(config.id, config.trigger_mode);
+            if self.trigger_mode(config.id) != config.trigger_mode {
+                reject(&mut report, i, IrqConfigError::InvalidTriggerMode);
+                continue;
+            }
+            if config.enabled {
+                self.set_enable(config.id, true);
+            }
+            report.applied += 1;
+        }
+        report
+    }
+
+    /// Lists the entries of a table of interrupt configurations that fail
+    /// validation, with their index in the table and the reason.
+    ///
+    /// [`Self::apply_irq_configs`] rejects them, and also the entries whose
+    /// trigger mode turns out to be fixed while applying them.
+    pub fn rejected_irq_configs<'a>(
+        &'a self,
+        table: &'a [IrqConfig],
+    ) -> impl Iterator<Item = (usize, IrqConfigError)> + 'a {
+        table
+            .iter()
+            .enumerate()
+            .filter_map(|(i, config)| self.validate_irq_config(config).err().map(|err| (i, err)))
+    }
+}
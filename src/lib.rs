@@ -7,9 +7,47 @@ use core::ops::Range;
 
 mod regs;
 
+#[cfg(feature = "acpi")]
+mod acpi;
+mod balance;
+mod bitmap;
+mod controller;
+#[cfg(feature = "fdt")]
+mod fdt;
 mod gic_v2;
+#[cfg(feature = "hal")]
+pub mod hal;
+mod handler_table;
+mod iidr;
+mod irq_config;
+mod nesting;
+mod quirks;
+mod state;
 
-pub use gic_v2::{GicCpuInterface, GicDistributor};
+#[cfg(feature = "acpi")]
+pub use acpi::{
+    Madt, MadtError, MadtGicc, MadtGicd, MadtWriter, VgicMadtConfig, MADT_TYPE_GICC, MADT_TYPE_GICD,
+};
+pub use balance::SpiBalancer;
+pub use bitmap::IrqBitmap;
+pub use controller::{AckedIrq, Gic, InterruptController};
+#[cfg(feature = "fdt")]
+pub use fdt::{
+    FdtBuilder, FdtError, FdtWriter, GicFdtInfo, GicRegion, VgicFdtConfig, FDT_MAX_STRING_LEN,
+    GIC_FDT_COMPATIBLES,
+};
+pub use gic_v2::{
+    GicCpuInterface, GicCpuInterfaceConfig, GicCpuInterfaceLayout, GicDistributor,
+    GicDistributorConfig,
+};
+pub use handler_table::{
+    IrqDispatch, IrqHandler, IrqHandlerTable, SharedIrqHandler, SharedIrqLine,
+};
+pub use iidr::{GicModel, GiccIidr, GicdIidr, IIDR_IMPLEMENTER_ARM};
+pub use irq_config::{IrqConfig, IrqConfigError, IrqConfigReport};
+pub use nesting::IrqNestState;
+pub use quirks::{quirks_of, GicQuirkEntry, GicQuirks, GIC_QUIRKS};
+pub use state::{GicCpuInterfaceState, GicDistributorBankedState, GicDistributorState};
 
 /// Interrupt ID 0-15 are used for SGIs (Software-generated interrupt).
 ///
@@ -31,6 +69,15 @@ pub const SPI_RANGE: Range<usize> = 32..1020;
 /// Maximum number of interrupts supported by the GIC.
 pub const GIC_MAX_IRQ: usize = 1024;
 
+/// Number of shared interrupts (SPIs).
+const SHARED_IRQ_NUM: usize = SPI_RANGE.end - SPI_RANGE.start;
+
+/// Maximum number of CPU interfaces supported by the GIC.
+pub const GIC_MAX_CPUS: usize = 8;
+
+/// Priority that [`GicDistributor::init_cpu`] gives to the SGIs and PPIs.
+pub const GIC_DEFAULT_PRIORITY: u8 = 0xa0;
+
 /// Number of bits used to configure the trigger mode for each interrupt.
 pub const GIC_CONFIG_BITS: usize = 2;
 
@@ -48,6 +95,30 @@ pub const GICC_CTLR_EN_BIT: u32 = 0x1;
 /// * 1: GICC_EOIR has priority drop functionality only. The GICC_DIR register has deactivate interrupt functionality.
 pub const GICC_CTLR_EOIMODENS_BIT: u32 = 1 << 9;
 
+/// GICC_CTLR register bits:
+///     bit 1 EnableGrp1:
+/// Enable for the signaling of Group 1 interrupts by the CPU interface to the connected processor.
+/// Only present in the Secure copy of GICC_CTLR, or if the GIC does not implement the Security Extensions.
+/// * 0: Disable signaling of Group 1 interrupts.
+/// * 1: Enable signaling of Group 1 interrupts.
+pub const GICC_CTLR_ENGRP1_BIT: u32 = 1 << 1;
+
+/// GICC_CTLR register bits:
+///     bit 5 FIQBypDis:
+/// When the signaling of FIQs by the CPU interface is disabled, this bit partly controls whether the
+/// bypass FIQ signal is signaled to the processor:
+/// * 0: Bypass FIQ signal is signaled to the processor.
+/// * 1: Bypass FIQ signal is not signaled to the processor.
+pub const GICC_CTLR_FIQBYPDIS_BIT: u32 = 1 << 5;
+
+/// GICC_CTLR register bits:
+///     bit 6 IRQBypDis:
+/// When the signaling of IRQs by the CPU interface is disabled, this bit partly controls whether the
+/// bypass IRQ signal is signaled to the processor:
+/// * 0: Bypass IRQ signal is signaled to the processor.
+/// * 1: Bypass IRQ signal is not signaled to the processor.
+pub const GICC_CTLR_IRQBYPDIS_BIT: u32 = 1 << 6;
+
 /// GICD_CTLR register bits:
 ///    bit 0 EnableGrp0:
 /// Enable for the signaling of Group 0 interrupts by the Distributor to the connected processors:
@@ -56,6 +127,7 @@ pub const GICC_CTLR_EOIMODENS_BIT: u32 = 1 << 9;
 const GICD_CTLR_EN_BIT: u32 = 0x1;
 
 /// Interrupt trigger mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TriggerMode {
     /// Edge-triggered.
     ///
@@ -71,6 +143,32 @@ pub enum TriggerMode {
     Level = 1,
 }
 
+/// Interrupt group, configured by GICD_IGROUPR.
+///
+/// If the GIC implements the Security Extensions, group 0 interrupts are
+/// Secure and group 1 interrupts are Non-secure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptGroup {
+    /// Group 0, usually signaled as FIQs.
+    Group0 = 0,
+    /// Group 1, usually signaled as IRQs.
+    Group1 = 1,
+}
+
+/// End of interrupt mode of the CPU interface (GICC_CTLR.EOImodeNS).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EoiMode {
+    /// A write to GICC_EOIR both drops the running priority and deactivates
+    /// the interrupt.
+    Combined,
+    /// A write to GICC_EOIR only drops the running priority, the interrupt
+    /// must then be deactivated by a write to GICC_DIR.
+    ///
+    /// This is the mode usually chosen by a hypervisor running at EL2, which
+    /// deactivates physical interrupts on behalf of its guests.
+    Split,
+}
+
 /// Different types of interrupt that the GIC handles.
 pub enum InterruptType {
     /// Software-generated interrupt.
@@ -114,3 +212,101 @@ pub const fn translate_irq(id: usize, int_type: InterruptType) -> Option<usize>
         }
     }
 }
+
+/// An interrupt decoded from a device tree interrupt specifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DtIrqSpec {
+    /// The GIC INTID.
+    pub id: usize,
+    /// The trigger mode, or `None` if the specifier leaves it unspecified.
+    pub trigger_mode: Option<TriggerMode>,
+    /// For PPIs, the mask of the CPU interfaces the interrupt is wired to, or
+    /// `0` if unspecified. Always `0` for SPIs.
+    pub cpu_mask: u8,
+}
+
+/// Decodes a 3-cell GIC interrupt specifier of a device tree, such as
+/// `interrupts = <0 33 4>`.
+///
+/// - The first cell is the interrupt type, `0` for SPIs and `1` for PPIs.
+/// - The second cell is the interrupt number within its type, translated
+///   to a GIC INTID by [`translate_irq`], so `<0 33 4>` is INTID 65.
+/// - The third cell holds the flags: bits 3:0 are the trigger type (`1`
+///   for rising edge, `2` for falling edge, `4` for high level and `8` for
+///   low level), and bits 15:8 the CPU mask of a PPI.
+///
+/// Returns `None` if the type, number or trigger type is invalid. The GIC
+/// only supports rising edge and high level SPIs, so falling edge and low
+/// level are invalid for SPIs. Device trees commonly describe PPIs, such as
+/// the architected timer, as low level, so for PPIs they are decoded as edge
+/// and level, like Linux does.
+pub const fn decode_dt_irq(cells: [u32; 3]) -> Option<DtIrqSpec> {
+    let [int_type, number, flags] = cells;
+    let int_type = match int_type {
+        0 => InterruptType::SPI,
+        1 => InterruptType::PPI,
+        _ => return None,
+    };
+    let is_ppi = matches!(int_type, InterruptType::PPI);
+    let cpu_mask = if is_ppi {
+        ((flags >> 8) & 0xff) as u8
+    } else {
+        0
+    };
+    let trigger_mode = match (flags & 0xf, is_ppi) {
+        (0, _) => None,
+        (1, _) | (2, true) => Some(TriggerMode::Edge),
+        (4, _) | (8, true) => Some(TriggerMode::Level),
+        _ => return None,
+    };
+    match translate_irq(number as usize, int_type) {
+        Some(id) => Some(DtIrqSpec {
+            id,
+            trigger_mode,
+            cpu_mask,
+        }),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_dt_irqs() {
+        let spec = decode_dt_irq([0, 33, 4]).unwrap();
+        assert_eq!(spec.id, 65);
+        assert_eq!(spec.trigger_mode, Some(TriggerMode::Level));
+        assert_eq!(spec.cpu_mask, 0);
+
+        let spec = decode_dt_irq([1, 14, 0xf01]).unwrap();
+        assert_eq!(spec.id, 30);
+        assert_eq!(spec.trigger_mode, Some(TriggerMode::Edge));
+        assert_eq!(spec.cpu_mask, 0xf);
+
+        // architected timer of the BCM2711, low level
+        let spec = decode_dt_irq([1, 13, 0xf08]).unwrap();
+        assert_eq!(spec.id, 29);
+        assert_eq!(spec.trigger_mode, Some(TriggerMode::Level));
+        assert_eq!(spec.cpu_mask, 0xf);
+        assert_eq!(
+            decode_dt_irq([1, 13, 0xf02]).unwrap().trigger_mode,
+            Some(TriggerMode::Edge)
+        );
+
+        assert_eq!(decode_dt_irq([0, 1, 0]).unwrap().trigger_mode, None);
+    }
+
+    #[test]
+    fn decode_invalid_dt_irqs() {
+        // unknown type
+        assert_eq!(decode_dt_irq([2, 0, 4]), None);
+        // out of range numbers
+        assert_eq!(decode_dt_irq([1, 16, 4]), None);
+        assert_eq!(decode_dt_irq([0, 988, 4]), None);
+        // falling edge and low level SPIs
+        assert_eq!(decode_dt_irq([0, 33, 2]), None);
+        assert_eq!(decode_dt_irq([0, 33, 8]), None);
+    }
+}
@@ -0,0 +1,64 @@
+//! Nesting state of preemptible interrupt handling.
+
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+/// The nesting state of the interrupts being handled on a CPU.
+///
+/// It is used by [`GicCpuInterface::handle_irq_nested`] to track the nesting
+/// depth, and the running priority of each nested interrupt, with the lowest
+/// priority interrupt at the bottom of the stack. The priority of interrupts
+/// nested deeper than `N` levels is not recorded, but they are still counted
+/// in the depth.
+///
+/// Each CPU must use its own state.
+///
+/// [`GicCpuInterface::handle_irq_nested`]: crate::GicCpuInterface::handle_irq_nested
+pub struct IrqNestState<const N: usize> {
+    depth: AtomicUsize,
+    priorities: [AtomicU8; N],
+}
+
+impl<const N: usize> IrqNestState<N> {
+    /// Creates a new state with no interrupts being handled.
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        Self {
+            depth: AtomicUsize::new(0),
+            priorities: [const { AtomicU8::new(0xff) }; N],
+        }
+    }
+
+    /// The number of interrupts currently being handled.
+    pub fn depth(&self) -> usize {
+        self.depth.load(Ordering::Relaxed)
+    }
+
+    /// The running priority of the interrupt at the given nesting level,
+    /// where level 0 is the first interrupt taken.
+    pub fn priority(&self, level: usize) -> Option<u8> {
+        if level < self.depth().min(N) {
+            Some(self.priorities[level].load(Ordering::Relaxed))
+        } else {
+            None
+        }
+    }
+
+    /// The running priority of the innermost interrupt being handled.
+    pub fn current_priority(&self) -> Option<u8> {
+        self.priority(self.depth().checked_sub(1)?)
+    }
+
+    pub(crate) fn push(&self, priority: u8) {
+        let level = self.depth.fetch_add(1, Ordering::Relaxed);
+        if let Some(slot) = self.priorities.get(level) {
+            slot.store(priority, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn pop(&self) {
+        let level = self.depth.fetch_sub(1, Ordering::Relaxed) - 1;
+        if let Some(slot) = self.priorities.get(level) {
+            slot.store(0xff, Ordering::Relaxed);
+        }
+    }
+}
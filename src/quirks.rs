@@ -0,0 +1,81 @@
+//! Known deviations of GIC implementations from the architecture.
+
+use core::ops::{BitOr, BitOrAssign};
+
+use crate::GicModel;
+
+/// A set of implementation quirks the driver has to work around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GicQuirks(u32);
+
+impl GicQuirks {
+    /// No quirks.
+    pub const NONE: Self = Self(0);
+    /// The CPU interface implements GICv1, without GICC_DIR, so priority drop
+    /// and deactivation cannot be split.
+    pub const NO_SPLIT_EOI: Self = Self(1 << 0);
+    /// Byte accesses to the distributor registers are broken, so that
+    /// byte-wide fields must be updated with word read-modify-writes. No
+    /// known implementation needs it, so it is never set from
+    /// [`GIC_QUIRKS`], but it can be given to
+    /// [`GicDistributor::add_quirks`](crate::GicDistributor::add_quirks) for
+    /// an integration that does.
+    pub const NO_BYTE_ACCESS: Self = Self(1 << 1);
+    /// Each 4KB page of the CPU interface is aliased over a 64KB frame. This
+    /// depends on the integration rather than on the implementation, so it
+    /// is never set from [`GIC_QUIRKS`].
+    ///
+    /// See [`GicCpuInterfaceLayout::Aliased64K`](crate::GicCpuInterfaceLayout::Aliased64K).
+    pub const GICC_ALIAS_64K: Self = Self(1 << 2);
+
+    /// Whether all the quirks in `other` are in the set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The union of two sets of quirks.
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitOr for GicQuirks {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+impl BitOrAssign for GicQuirks {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = self.union(rhs);
+    }
+}
+
+/// An entry of the [`GIC_QUIRKS`] table.
+#[derive(Debug, Clone, Copy)]
+pub struct GicQuirkEntry {
+    /// The implementation the entry applies to.
+    pub model: GicModel,
+    /// A description of the quirks.
+    pub desc: &'static str,
+    /// The quirks of the implementation.
+    pub quirks: GicQuirks,
+}
+
+/// The quirks of known GIC implementations, consulted when initializing the
+/// distributor and the CPU interfaces.
+pub const GIC_QUIRKS: &[GicQuirkEntry] = &[GicQuirkEntry {
+    model: GicModel::Gic390,
+    desc: "GIC-390: GICv1 CPU interface, without GICC_DIR",
+    quirks: GicQuirks::NO_SPLIT_EOI,
+}];
+
+/// Returns the quirks of the given implementation, from [`GIC_QUIRKS`].
+pub fn quirks_of(model: GicModel) -> GicQuirks {
+    GIC_QUIRKS
+        .iter()
+        .filter(|entry| entry.model == model)
+        .fold(GicQuirks::NONE, |quirks, entry| quirks | entry.quirks)
+}
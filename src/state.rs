@@ -0,0 +1,53 @@
+//! Saved register state of the GIC, for system suspend and resume.
+
+use crate::GIC_MAX_IRQ;
+
+/// Saved state of the distributor registers shared by all CPUs.
+///
+/// Returned by [`GicDistributor::save_state`], it holds GICD_CTLR and the SPI
+/// configuration (group, enable, priority, target and trigger mode) of the
+/// interrupts below the discovered [`GicDistributor::max_irqs`].
+///
+/// [`GicDistributor::save_state`]: crate::GicDistributor::save_state
+/// [`GicDistributor::max_irqs`]: crate::GicDistributor::max_irqs
+#[derive(Clone)]
+pub struct GicDistributorState {
+    pub(crate) max_irqs: usize,
+    pub(crate) ctlr: u32,
+    pub(crate) igroupr: [u32; GIC_MAX_IRQ / 32],
+    pub(crate) isenabler: [u32; GIC_MAX_IRQ / 32],
+    pub(crate) ipriorityr: [u32; GIC_MAX_IRQ / 4],
+    pub(crate) itargetsr: [u32; GIC_MAX_IRQ / 4],
+    pub(crate) icfgr: [u32; GIC_MAX_IRQ / 16],
+}
+
+/// Saved state of the distributor registers banked for a CPU.
+///
+/// Returned by [`GicDistributor::save_banked_state`], it holds the SGI and
+/// PPI configuration (group, enable, priority and PPI trigger mode) of the
+/// calling CPU.
+///
+/// [`GicDistributor::save_banked_state`]: crate::GicDistributor::save_banked_state
+#[derive(Clone)]
+pub struct GicDistributorBankedState {
+    pub(crate) igroupr0: u32,
+    pub(crate) isenabler0: u32,
+    pub(crate) ipriorityr: [u32; 8],
+    pub(crate) icfgr1: u32,
+}
+
+/// Saved state of the CPU interface registers.
+///
+/// Returned by [`GicCpuInterface::save_state`], it holds GICC_CTLR, GICC_PMR,
+/// the binary point registers and the active priorities registers.
+///
+/// [`GicCpuInterface::save_state`]: crate::GicCpuInterface::save_state
+#[derive(Clone)]
+pub struct GicCpuInterfaceState {
+    pub(crate) ctlr: u32,
+    pub(crate) pmr: u32,
+    pub(crate) bpr: u32,
+    pub(crate) abpr: u32,
+    pub(crate) apr: [u32; 4],
+    pub(crate) nsapr: [u32; 4],
+}